@@ -0,0 +1 @@
+ALTER TABLE posts DROP INDEX content_fulltext_index;
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE posts ADD FULLTEXT INDEX content_fulltext_index (content) WITH PARSER ngram;
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE user_handle_histories;
\ No newline at end of file
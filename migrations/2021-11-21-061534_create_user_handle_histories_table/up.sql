@@ -0,0 +1,14 @@
+CREATE TABLE user_handle_histories
+(
+    id             CHAR(36) PRIMARY KEY,
+    user_id        CHAR(36)    NOT NULL,
+    id_name        VARCHAR(20) NOT NULL,
+    reserved_until TIMESTAMP   NOT NULL,
+    created_at     TIMESTAMP        DEFAULT CURRENT_TIMESTAMP,
+    updated_at     TIMESTAMP        DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    deleted_at     TIMESTAMP   NULL DEFAULT NULL
+);
+
+CREATE INDEX id_name_index ON user_handle_histories (id_name);
+CREATE INDEX user_id_index ON user_handle_histories (user_id);
+ALTER TABLE user_handle_histories ADD FOREIGN KEY user_id_foreign (user_id) REFERENCES users (id);
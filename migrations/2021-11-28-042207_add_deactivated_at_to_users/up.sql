@@ -0,0 +1 @@
+ALTER TABLE users ADD deactivated_at TIMESTAMP NULL DEFAULT NULL;
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE user_data_exports;
\ No newline at end of file
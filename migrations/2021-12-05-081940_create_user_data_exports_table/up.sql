@@ -0,0 +1,16 @@
+CREATE TABLE user_data_exports
+(
+    id             CHAR(36) PRIMARY KEY,
+    user_id        CHAR(36)     NOT NULL,
+    status         VARCHAR(20)  NOT NULL,
+    file_path      VARCHAR(255) NULL DEFAULT NULL,
+    download_token CHAR(64)     NULL DEFAULT NULL,
+    expired_at     TIMESTAMP    NULL DEFAULT NULL,
+    created_at     TIMESTAMP         DEFAULT CURRENT_TIMESTAMP,
+    updated_at     TIMESTAMP         DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    deleted_at     TIMESTAMP    NULL DEFAULT NULL
+);
+
+ALTER TABLE user_data_exports ADD UNIQUE (download_token);
+CREATE INDEX user_id_index ON user_data_exports (user_id);
+ALTER TABLE user_data_exports ADD FOREIGN KEY user_id_foreign (user_id) REFERENCES user_credentials (id);
@@ -0,0 +1,4 @@
+DROP TABLE post_import_errors;
+DROP TABLE post_imports;
+ALTER TABLE posts DROP INDEX author_import_source_index;
+ALTER TABLE posts DROP COLUMN import_source_id;
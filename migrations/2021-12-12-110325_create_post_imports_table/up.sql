@@ -0,0 +1,34 @@
+ALTER TABLE posts ADD import_source_id VARCHAR(255) NULL DEFAULT NULL;
+ALTER TABLE posts ADD UNIQUE author_import_source_index (author_id, import_source_id);
+
+CREATE TABLE post_imports
+(
+    id             CHAR(36) PRIMARY KEY,
+    user_id        CHAR(36)    NOT NULL,
+    format         VARCHAR(20) NOT NULL,
+    status         VARCHAR(20) NOT NULL,
+    total_count    INT         NOT NULL DEFAULT 0,
+    imported_count INT         NOT NULL DEFAULT 0,
+    skipped_count  INT         NOT NULL DEFAULT 0,
+    failed_count   INT         NOT NULL DEFAULT 0,
+    created_at     TIMESTAMP        DEFAULT CURRENT_TIMESTAMP,
+    updated_at     TIMESTAMP        DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    deleted_at     TIMESTAMP   NULL DEFAULT NULL
+);
+
+CREATE INDEX user_id_index ON post_imports (user_id);
+ALTER TABLE post_imports ADD FOREIGN KEY user_id_foreign (user_id) REFERENCES users (id);
+
+CREATE TABLE post_import_errors
+(
+    id         CHAR(36) PRIMARY KEY,
+    import_id  CHAR(36)     NOT NULL,
+    row_number INT          NOT NULL,
+    message    VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP         DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP         DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    deleted_at TIMESTAMP    NULL DEFAULT NULL
+);
+
+CREATE INDEX import_id_index ON post_import_errors (import_id);
+ALTER TABLE post_import_errors ADD FOREIGN KEY import_id_foreign (import_id) REFERENCES post_imports (id);
@@ -0,0 +1,4 @@
+DROP TABLE user_refresh_tokens;
+
+DROP INDEX family_id_index ON user_tokens;
+ALTER TABLE user_tokens DROP COLUMN family_id;
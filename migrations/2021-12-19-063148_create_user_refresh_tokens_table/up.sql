@@ -0,0 +1,20 @@
+ALTER TABLE user_tokens ADD family_id CHAR(36) NULL DEFAULT NULL AFTER user_id;
+UPDATE user_tokens SET family_id = UUID();
+ALTER TABLE user_tokens MODIFY family_id CHAR(36) NOT NULL;
+CREATE INDEX family_id_index ON user_tokens (family_id);
+
+CREATE TABLE user_refresh_tokens
+(
+    token      CHAR(36) PRIMARY KEY,
+    user_id    CHAR(36)  NOT NULL,
+    family_id  CHAR(36)  NOT NULL,
+    used_at    TIMESTAMP NULL DEFAULT NULL,
+    expired_at TIMESTAMP NOT NULL,
+    created_at TIMESTAMP      DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP      DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    deleted_at TIMESTAMP NULL DEFAULT NULL
+);
+
+CREATE INDEX user_id_index ON user_refresh_tokens (user_id);
+CREATE INDEX family_id_index ON user_refresh_tokens (family_id);
+ALTER TABLE user_refresh_tokens ADD FOREIGN KEY user_id_foreign (user_id) REFERENCES user_credentials (id);
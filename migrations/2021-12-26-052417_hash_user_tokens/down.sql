@@ -0,0 +1 @@
+ALTER TABLE user_refresh_tokens MODIFY token CHAR(36);
\ No newline at end of file
@@ -0,0 +1 @@
+ALTER TABLE user_refresh_tokens MODIFY token CHAR(64);
\ No newline at end of file
@@ -0,0 +1,22 @@
+CREATE TABLE user_sessions
+(
+    id           CHAR(36) PRIMARY KEY,
+    user_id      CHAR(36)     NOT NULL,
+    user_agent   VARCHAR(255) NULL DEFAULT NULL,
+    ip_address   VARCHAR(45)  NULL DEFAULT NULL,
+    device_name  VARCHAR(100) NULL DEFAULT NULL,
+    last_used_at TIMESTAMP    NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    expired_at   TIMESTAMP    NOT NULL,
+    created_at   TIMESTAMP         DEFAULT CURRENT_TIMESTAMP,
+    updated_at   TIMESTAMP         DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    deleted_at   TIMESTAMP    NULL DEFAULT NULL
+);
+
+CREATE INDEX user_id_index ON user_sessions (user_id);
+ALTER TABLE user_sessions ADD FOREIGN KEY user_id_foreign (user_id) REFERENCES user_credentials (id);
+
+INSERT INTO user_sessions (id, user_id, last_used_at, expired_at, created_at)
+SELECT family_id, user_id, MAX(created_at), MAX(expired_at), MIN(created_at)
+FROM user_tokens
+WHERE deleted_at IS NULL
+GROUP BY family_id, user_id;
\ No newline at end of file
@@ -0,0 +1,17 @@
+CREATE TABLE personal_access_tokens
+(
+    id           CHAR(36) PRIMARY KEY,
+    user_id      CHAR(36)     NOT NULL,
+    name         VARCHAR(100) NOT NULL,
+    token        CHAR(64)     NOT NULL,
+    scopes       VARCHAR(255) NOT NULL,
+    last_used_at TIMESTAMP    NULL DEFAULT NULL,
+    expired_at   TIMESTAMP    NULL DEFAULT NULL,
+    created_at   TIMESTAMP         DEFAULT CURRENT_TIMESTAMP,
+    updated_at   TIMESTAMP         DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    deleted_at   TIMESTAMP    NULL DEFAULT NULL
+);
+
+ALTER TABLE personal_access_tokens ADD UNIQUE (token);
+CREATE INDEX user_id_index ON personal_access_tokens (user_id);
+ALTER TABLE personal_access_tokens ADD FOREIGN KEY user_id_foreign (user_id) REFERENCES user_credentials (id);
\ No newline at end of file
@@ -0,0 +1,6 @@
+DROP INDEX app_id_index ON user_sessions;
+ALTER TABLE user_sessions DROP COLUMN scopes;
+ALTER TABLE user_sessions DROP COLUMN app_id;
+
+DROP TABLE oauth_authorization_codes;
+DROP TABLE oauth_apps;
\ No newline at end of file
@@ -0,0 +1,39 @@
+CREATE TABLE oauth_apps
+(
+    id            CHAR(36) PRIMARY KEY,
+    owner_id      CHAR(36)      NOT NULL,
+    name          VARCHAR(100)  NOT NULL,
+    client_secret CHAR(64)      NULL DEFAULT NULL,
+    redirect_uris VARCHAR(1000) NOT NULL,
+    created_at    TIMESTAMP          DEFAULT CURRENT_TIMESTAMP,
+    updated_at    TIMESTAMP          DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    deleted_at    TIMESTAMP     NULL DEFAULT NULL
+);
+
+CREATE INDEX owner_id_index ON oauth_apps (owner_id);
+ALTER TABLE oauth_apps ADD FOREIGN KEY owner_id_foreign (owner_id) REFERENCES user_credentials (id);
+
+CREATE TABLE oauth_authorization_codes
+(
+    code           CHAR(64) PRIMARY KEY,
+    app_id         CHAR(36)     NOT NULL,
+    user_id        CHAR(36)     NOT NULL,
+    redirect_uri   VARCHAR(255) NOT NULL,
+    scopes         VARCHAR(255) NOT NULL,
+    code_challenge VARCHAR(128) NOT NULL,
+    session_id     CHAR(36)     NULL DEFAULT NULL,
+    used_at        TIMESTAMP    NULL DEFAULT NULL,
+    expired_at     TIMESTAMP    NOT NULL,
+    created_at     TIMESTAMP         DEFAULT CURRENT_TIMESTAMP,
+    updated_at     TIMESTAMP         DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    deleted_at     TIMESTAMP    NULL DEFAULT NULL
+);
+
+CREATE INDEX app_id_index ON oauth_authorization_codes (app_id);
+CREATE INDEX user_id_index ON oauth_authorization_codes (user_id);
+ALTER TABLE oauth_authorization_codes ADD FOREIGN KEY app_id_foreign (app_id) REFERENCES oauth_apps (id);
+ALTER TABLE oauth_authorization_codes ADD FOREIGN KEY user_id_foreign (user_id) REFERENCES user_credentials (id);
+
+ALTER TABLE user_sessions ADD app_id CHAR(36) NULL DEFAULT NULL AFTER user_id;
+ALTER TABLE user_sessions ADD scopes VARCHAR(255) NULL DEFAULT NULL AFTER app_id;
+CREATE INDEX app_id_index ON user_sessions (app_id);
\ No newline at end of file
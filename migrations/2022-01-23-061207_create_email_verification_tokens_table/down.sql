@@ -0,0 +1,3 @@
+ALTER TABLE user_credentials DROP COLUMN email_verified_at;
+
+DROP TABLE email_verification_tokens;
\ No newline at end of file
@@ -0,0 +1,18 @@
+CREATE TABLE email_verification_tokens
+(
+    token      CHAR(64) PRIMARY KEY,
+    user_id    CHAR(36)     NOT NULL,
+    email      VARCHAR(255) NOT NULL,
+    expired_at TIMESTAMP    NOT NULL,
+    created_at TIMESTAMP         DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP         DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    deleted_at TIMESTAMP    NULL DEFAULT NULL
+);
+
+CREATE INDEX user_id_index ON email_verification_tokens (user_id);
+ALTER TABLE email_verification_tokens ADD FOREIGN KEY user_id_foreign (user_id) REFERENCES user_credentials (id);
+
+ALTER TABLE user_credentials ADD email_verified_at TIMESTAMP NULL DEFAULT NULL AFTER email;
+
+-- Accounts registered before verification existed keep working as they did.
+UPDATE user_credentials SET email_verified_at = created_at;
\ No newline at end of file
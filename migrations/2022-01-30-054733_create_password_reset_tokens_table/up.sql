@@ -0,0 +1,12 @@
+CREATE TABLE password_reset_tokens
+(
+    token      CHAR(64) PRIMARY KEY,
+    user_id    CHAR(36)  NOT NULL,
+    expired_at TIMESTAMP NOT NULL,
+    created_at TIMESTAMP      DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP      DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    deleted_at TIMESTAMP NULL DEFAULT NULL
+);
+
+CREATE INDEX user_id_index ON password_reset_tokens (user_id);
+ALTER TABLE password_reset_tokens ADD FOREIGN KEY user_id_foreign (user_id) REFERENCES user_credentials (id);
\ No newline at end of file
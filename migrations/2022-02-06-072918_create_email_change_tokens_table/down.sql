@@ -0,0 +1 @@
+DROP TABLE email_change_tokens;
\ No newline at end of file
@@ -0,0 +1,13 @@
+CREATE TABLE email_change_tokens
+(
+    token      CHAR(64) PRIMARY KEY,
+    user_id    CHAR(36)     NOT NULL,
+    new_email  VARCHAR(255) NOT NULL,
+    expired_at TIMESTAMP    NOT NULL,
+    created_at TIMESTAMP         DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP         DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    deleted_at TIMESTAMP    NULL DEFAULT NULL
+);
+
+CREATE INDEX user_id_index ON email_change_tokens (user_id);
+ALTER TABLE email_change_tokens ADD FOREIGN KEY user_id_foreign (user_id) REFERENCES user_credentials (id);
\ No newline at end of file
@@ -0,0 +1,6 @@
+DROP TABLE two_factor_challenges;
+DROP TABLE user_recovery_codes;
+
+ALTER TABLE user_credentials DROP COLUMN totp_last_used_step;
+ALTER TABLE user_credentials DROP COLUMN totp_enabled_at;
+ALTER TABLE user_credentials DROP COLUMN totp_secret;
\ No newline at end of file
@@ -0,0 +1,29 @@
+ALTER TABLE user_credentials ADD totp_secret VARCHAR(64) NULL DEFAULT NULL AFTER email_verified_at;
+ALTER TABLE user_credentials ADD totp_enabled_at TIMESTAMP NULL DEFAULT NULL AFTER totp_secret;
+ALTER TABLE user_credentials ADD totp_last_used_step BIGINT NULL DEFAULT NULL AFTER totp_enabled_at;
+
+CREATE TABLE user_recovery_codes
+(
+    code       CHAR(64) PRIMARY KEY,
+    user_id    CHAR(36)  NOT NULL,
+    used_at    TIMESTAMP NULL DEFAULT NULL,
+    created_at TIMESTAMP      DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX user_id_index ON user_recovery_codes (user_id);
+ALTER TABLE user_recovery_codes ADD FOREIGN KEY user_id_foreign (user_id) REFERENCES user_credentials (id);
+
+CREATE TABLE two_factor_challenges
+(
+    token           CHAR(64) PRIMARY KEY,
+    user_id         CHAR(36)     NOT NULL,
+    device_name     VARCHAR(100) NULL DEFAULT NULL,
+    failed_attempts INT          NOT NULL DEFAULT 0,
+    expired_at      TIMESTAMP    NOT NULL,
+    created_at      TIMESTAMP         DEFAULT CURRENT_TIMESTAMP,
+    updated_at      TIMESTAMP         DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    deleted_at      TIMESTAMP    NULL DEFAULT NULL
+);
+
+CREATE INDEX user_id_index ON two_factor_challenges (user_id);
+ALTER TABLE two_factor_challenges ADD FOREIGN KEY user_id_foreign (user_id) REFERENCES user_credentials (id);
\ No newline at end of file
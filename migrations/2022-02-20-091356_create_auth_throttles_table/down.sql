@@ -0,0 +1 @@
+DROP TABLE auth_throttles;
\ No newline at end of file
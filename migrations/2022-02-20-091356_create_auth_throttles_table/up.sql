@@ -0,0 +1,11 @@
+CREATE TABLE auth_throttles
+(
+    throttle_key   CHAR(64) PRIMARY KEY,
+    failed_count   INT       NOT NULL DEFAULT 0,
+    locked_until   TIMESTAMP NULL DEFAULT NULL,
+    last_failed_at TIMESTAMP NULL DEFAULT NULL,
+    created_at     TIMESTAMP      DEFAULT CURRENT_TIMESTAMP,
+    updated_at     TIMESTAMP      DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP
+);
+
+CREATE INDEX last_failed_at_index ON auth_throttles (last_failed_at);
\ No newline at end of file
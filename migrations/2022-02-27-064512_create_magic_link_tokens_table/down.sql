@@ -0,0 +1,3 @@
+DROP TABLE magic_link_tokens;
+
+ALTER TABLE user_credentials DROP COLUMN magic_link_enabled;
\ No newline at end of file
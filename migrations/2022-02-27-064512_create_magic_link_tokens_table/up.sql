@@ -0,0 +1,15 @@
+ALTER TABLE user_credentials ADD magic_link_enabled BOOLEAN NOT NULL DEFAULT TRUE AFTER totp_last_used_step;
+
+CREATE TABLE magic_link_tokens
+(
+    token       CHAR(64) PRIMARY KEY,
+    user_id     CHAR(36)     NOT NULL,
+    device_name VARCHAR(100) NULL DEFAULT NULL,
+    expired_at  TIMESTAMP    NOT NULL,
+    created_at  TIMESTAMP         DEFAULT CURRENT_TIMESTAMP,
+    updated_at  TIMESTAMP         DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    deleted_at  TIMESTAMP    NULL DEFAULT NULL
+);
+
+CREATE INDEX user_id_index ON magic_link_tokens (user_id);
+ALTER TABLE magic_link_tokens ADD FOREIGN KEY user_id_foreign (user_id) REFERENCES user_credentials (id);
\ No newline at end of file
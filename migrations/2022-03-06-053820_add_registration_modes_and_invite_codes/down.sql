@@ -0,0 +1,7 @@
+DROP TABLE invite_codes;
+
+DROP INDEX invited_by_index ON user_credentials;
+ALTER TABLE user_credentials DROP COLUMN invite_code_id;
+ALTER TABLE user_credentials DROP COLUMN invited_by;
+ALTER TABLE user_credentials DROP COLUMN approved_at;
+ALTER TABLE user_credentials DROP COLUMN is_admin;
\ No newline at end of file
@@ -0,0 +1,25 @@
+ALTER TABLE user_credentials ADD is_admin BOOLEAN NOT NULL DEFAULT FALSE AFTER magic_link_enabled;
+ALTER TABLE user_credentials ADD approved_at TIMESTAMP NULL DEFAULT NULL AFTER is_admin;
+ALTER TABLE user_credentials ADD invited_by CHAR(36) NULL DEFAULT NULL AFTER approved_at;
+ALTER TABLE user_credentials ADD invite_code_id CHAR(36) NULL DEFAULT NULL AFTER invited_by;
+
+UPDATE user_credentials SET approved_at = created_at;
+
+CREATE INDEX invited_by_index ON user_credentials (invited_by);
+
+CREATE TABLE invite_codes
+(
+    id         CHAR(36) PRIMARY KEY,
+    inviter_id CHAR(36)  NOT NULL,
+    code       CHAR(64)  NOT NULL,
+    max_uses   INT       NOT NULL,
+    used_count INT       NOT NULL DEFAULT 0,
+    expired_at TIMESTAMP NULL DEFAULT NULL,
+    created_at TIMESTAMP      DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP      DEFAULT CURRENT_TIMESTAMP ON UPDATE CURRENT_TIMESTAMP,
+    deleted_at TIMESTAMP NULL DEFAULT NULL
+);
+
+ALTER TABLE invite_codes ADD UNIQUE (code);
+CREATE INDEX inviter_id_index ON invite_codes (inviter_id);
+ALTER TABLE invite_codes ADD FOREIGN KEY inviter_id_foreign (inviter_id) REFERENCES user_credentials (id);
\ No newline at end of file
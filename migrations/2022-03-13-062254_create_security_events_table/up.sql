@@ -0,0 +1,14 @@
+CREATE TABLE security_events
+(
+    id         BIGINT AUTO_INCREMENT PRIMARY KEY,
+    user_id    CHAR(36)     NOT NULL,
+    event_type VARCHAR(50)  NOT NULL,
+    detail     VARCHAR(255) NULL DEFAULT NULL,
+    ip_address VARCHAR(45)  NULL DEFAULT NULL,
+    user_agent VARCHAR(255) NULL DEFAULT NULL,
+    created_at TIMESTAMP         DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX user_id_index ON security_events (user_id, id);
+CREATE INDEX created_at_index ON security_events (created_at);
+ALTER TABLE security_events ADD FOREIGN KEY user_id_foreign (user_id) REFERENCES user_credentials (id);
\ No newline at end of file
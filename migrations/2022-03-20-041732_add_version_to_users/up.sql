@@ -0,0 +1 @@
+ALTER TABLE users ADD version INT NOT NULL DEFAULT 0;
\ No newline at end of file
@@ -1,12 +1,28 @@
-use actix_web::HttpResponse;
+use actix_web::{HttpRequest, HttpResponse};
+use actix_web::http::header::{self, EntityTag, ETag, Header, IfMatch, IfNoneMatch};
+use log::error;
 use maplit::hashmap;
 
+use crate::DBConPool;
 use crate::models::error;
+use crate::models::security_event::{SecurityEvent, SecurityEventType};
+use crate::models::user_session::SessionMetadata;
 use crate::services::token_authentication::AuthorizedUser;
+use crate::services::trusted_proxy;
 
+pub mod admin_controller;
 pub mod auth_controller;
+pub mod export_controller;
+pub mod import_controller;
+pub mod invite_code_controller;
+pub mod oauth_controller;
+pub mod personal_access_token_controller;
 pub mod user_controller;
 pub mod post_controller;
+pub mod search_controller;
+pub mod security_event_controller;
+pub mod session_controller;
+pub mod two_factor_controller;
 
 fn invalid_uuid_response() -> HttpResponse {
     HttpResponse::BadRequest().json(
@@ -20,6 +36,74 @@ fn parse_error_response() -> HttpResponse {
     )
 }
 
+fn deactivated_user_response() -> HttpResponse {
+    error::ApiError::new(error::ApiErrorCode::AccountDeactivated, "User is deactivated.").error_response()
+}
+
+fn email_not_verified_response() -> HttpResponse {
+    error::ApiError::new(error::ApiErrorCode::EmailNotVerified, "Verify your email first.").error_response()
+}
+
+fn admin_only_response() -> HttpResponse {
+    error::ApiError::new(error::ApiErrorCode::PermissionDenied, "Only administrators can do this.").error_response()
+}
+
 fn is_created_user(authorized_user: &AuthorizedUser) -> bool {
     authorized_user.user.is_some()
-}
\ No newline at end of file
+}
+
+fn session_metadata(request: &HttpRequest, device_name: Option<String>) -> SessionMetadata {
+    SessionMetadata {
+        user_agent: request.headers().get(header::USER_AGENT).and_then(|h| h.to_str().ok()).map(|h| h.to_string()),
+        ip_address: client_ip_address(request),
+        device_name,
+    }
+}
+
+fn client_ip_address(request: &HttpRequest) -> Option<String> {
+    trusted_proxy::client_ip(request).map(|ip| ip.to_string())
+}
+
+// A lost audit entry is logged rather than failing the action it describes.
+fn record_security_event(request: &HttpRequest, user_id: &String, event_type: SecurityEventType, detail: Option<String>, db: &DBConPool) {
+    if let Err(e) = SecurityEvent::record(user_id, event_type, detail, &session_metadata(request, None), db) {
+        error!("failed to record security event ({}, {}): {:?}", user_id, event_type.as_str(), e);
+    }
+}
+
+fn too_many_attempts_response(retry_after: i64) -> HttpResponse {
+    let mut response = error::ApiError::new(error::ApiErrorCode::RateLimited, "Too many attempts. Try again later.").error_response();
+    response.headers_mut().insert(header::RETRY_AFTER, header::HeaderValue::from(retry_after));
+    
+    response
+}
+
+fn is_not_modified(request: &HttpRequest, entity_tag: &EntityTag) -> bool {
+    match IfNoneMatch::parse(request) {
+        Ok(IfNoneMatch::Any) => true,
+        Ok(IfNoneMatch::Items(tags)) => tags.iter().any(|t| t.weak_eq(entity_tag)),
+        Err(_) => false,
+    }
+}
+
+fn is_precondition_failed(request: &HttpRequest, entity_tag: &EntityTag) -> bool {
+    match IfMatch::parse(request) {
+        // An absent `If-Match` header is parsed as an empty list.
+        Ok(IfMatch::Items(tags)) if !tags.is_empty() => !tags.iter().any(|t| t.strong_eq(entity_tag)),
+        _ => false,
+    }
+}
+
+/// Whether the client sent entity tags to match, as opposed to no header or `*`, in which case the update
+/// itself has to be made conditional on the version those tags were checked against.
+fn has_if_match_tags(request: &HttpRequest) -> bool {
+    matches!(IfMatch::parse(request), Ok(IfMatch::Items(tags)) if !tags.is_empty())
+}
+
+fn not_modified_response(entity_tag: EntityTag) -> HttpResponse {
+    HttpResponse::NotModified().set(ETag(entity_tag)).finish()
+}
+
+fn precondition_failed_response() -> HttpResponse {
+    error::ApiError::new(error::ApiErrorCode::PreconditionFailed, "Resource was modified by another request.").error_response()
+}
@@ -0,0 +1,74 @@
+use actix_web::{HttpResponse, Responder, web};
+use maplit::hashmap;
+
+use crate::controllers::{admin_only_response, invalid_uuid_response};
+use crate::DBConPool;
+use crate::models::error::{ApiError, ApiErrorCode};
+use crate::models::user_credential::UserCredential;
+use crate::services::mailer::{self, Mail, SharedMailer};
+use crate::services::token_authentication::AuthorizedUser;
+
+pub async fn registrations_index(authorized_user: web::ReqData<AuthorizedUser>, db: web::Data<DBConPool>) -> impl Responder {
+    if !authorized_user.credential.is_admin {
+        return admin_only_response();
+    }
+    
+    match UserCredential::fetch_pending_approval_list(&db) {
+        Ok(credentials) => HttpResponse::Ok().json(
+            hashmap! { "registrations" => credentials }
+        ),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+pub async fn approve_registration(user_id: Option<web::Path<uuid::Uuid>>, authorized_user: web::ReqData<AuthorizedUser>, mailer: web::Data<SharedMailer>, db: web::Data<DBConPool>) -> impl Responder {
+    if !authorized_user.credential.is_admin {
+        return admin_only_response();
+    }
+    let user_id = match user_id {
+        None => return invalid_uuid_response(),
+        Some(u) => u
+    };
+    
+    let credential = match UserCredential::fetch_pending_approval_by_id(&user_id.to_string(), &db) {
+        Ok(c) => c,
+        Err(diesel::NotFound) => return registration_not_found_response(),
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    
+    match credential.approve(&db) {
+        Ok(0) => registration_not_found_response(),
+        Ok(_) => {
+            mailer::send_in_background(mailer.get_ref().clone(), Mail {
+                to: credential.email,
+                subject: "Your account was approved".to_string(),
+                body: "Your registration was approved, so you can log in now.\n".to_string(),
+            });
+            HttpResponse::NoContent().finish()
+        }
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+pub async fn reject_registration(user_id: Option<web::Path<uuid::Uuid>>, authorized_user: web::ReqData<AuthorizedUser>, db: web::Data<DBConPool>) -> impl Responder {
+    if !authorized_user.credential.is_admin {
+        return admin_only_response();
+    }
+    let user_id = match user_id {
+        None => return invalid_uuid_response(),
+        Some(u) => u
+    };
+    
+    let result = UserCredential::fetch_pending_approval_by_id(&user_id.to_string(), &db)
+        .and_then(|c| c.reject(&db));
+    
+    match result {
+        Ok(0) | Err(diesel::NotFound) => registration_not_found_response(),
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+fn registration_not_found_response() -> HttpResponse {
+    ApiError::new(ApiErrorCode::NotFound, "Registration not found.").error_response()
+}
\ No newline at end of file
@@ -1,53 +1,379 @@
-use actix_web::{HttpResponse, Responder, web};
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+use log::error;
 use maplit::hashmap;
 
-use crate::controllers::parse_error_response;
+use crate::controllers::{client_ip_address, parse_error_response, record_security_event, session_metadata, too_many_attempts_response};
 use crate::DBConPool;
+use crate::models::auth_throttle::*;
+use crate::models::email_change_token::*;
+use crate::models::email_verification_token::*;
 use crate::models::error::*;
+use crate::models::magic_link_token::*;
+use crate::models::password_reset_token::*;
+use crate::models::registration_mode::RegistrationMode;
+use crate::models::security_event::SecurityEventType;
+use crate::models::two_factor_challenge::*;
+use crate::models::user::User;
 use crate::models::user_credential::*;
+use crate::models::user_refresh_token::*;
+use crate::models::user_session::*;
 use crate::models::user_token::*;
+use crate::services::mailer::{self, Mail, SharedMailer};
+use crate::services::password_hashing::PasswordHashingPool;
+use crate::services::session_cookie;
 use crate::services::token_authentication::AuthorizedUser;
 
-pub async fn login(credential: Option<web::Json<InputUserCredential>>, db: web::Data<DBConPool>) -> impl Responder {
+pub async fn login(req: HttpRequest, input: Option<web::Json<InputLogin>>, mailer: web::Data<SharedMailer>, hashing_pool: web::Data<PasswordHashingPool>, db: web::Data<DBConPool>) -> impl Responder {
+    let InputLogin { credential, device_name } = match input {
+        Some(i) => i.into_inner(),
+        None => return parse_error_response()
+    };
+    
+    let throttle_keys = login_throttle_keys(&req, &credential.email);
+    
+    match AuthThrottle::retry_after(&throttle_keys, &db) {
+        Ok(Some(retry_after)) => return too_many_attempts_response(retry_after),
+        Ok(None) => {}
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    }
+    
+    let email = credential.email.clone();
+    let database = db.clone();
+    let verification = hashing_pool.run(move || {
+        let user = UserCredential::verify_with_input(&credential, &database);
+        let is_deleted = user.is_err() && UserCredential::verify_deleted_with_input(&credential, &database).is_ok();
+        
+        (user, is_deleted)
+    }).await;
+    
+    match verification {
+        Ok((Ok(u), _)) => {
+            reset_login_throttle(&throttle_keys, &db);
+            login_response(&req, &u, device_name, &db)
+        }
+        Ok((Err(_), true)) => {
+            ApiError::new(ApiErrorCode::AccountDeleted, "Account is scheduled for deletion. Restore it to log in.").error_response()
+        }
+        Err(_) => HttpResponse::InternalServerError().finish(),
+        Ok((Err(_), false)) => {
+            record_login_failure(&req, &throttle_keys, &email, mailer.get_ref(), &db);
+            
+            HttpResponse::Unauthorized().json(
+                hashmap! {
+                    "error" => ApiError::new(ApiErrorCode::AuthFailed, "Invalid credentials.")
+                }
+            )
+        }
+    }
+}
+
+pub async fn restore(req: HttpRequest, credential: Option<web::Json<InputUserCredential>>, mailer: web::Data<SharedMailer>, hashing_pool: web::Data<PasswordHashingPool>, db: web::Data<DBConPool>) -> impl Responder {
     let credential = match credential {
-        Some(c) => c,
+        Some(c) => c.into_inner(),
         None => return parse_error_response()
     };
+    let email = credential.email.clone();
     
-    let user = UserCredential::verify_with_input(&credential, &db);
+    let throttle_keys = login_throttle_keys(&req, &email);
     
-    match user {
-        Ok(u) => HttpResponse::Ok().json(
-            hashmap! {
-                "token" => issue_user_token(&u.id, &db)
-            }
-        ),
-        Err(_) => HttpResponse::Unauthorized().json(
-            hashmap! {
-                "error" => ApiError::new(ApiErrorCode::AuthFailed, "Invalid credentials.")
+    match AuthThrottle::retry_after(&throttle_keys, &db) {
+        Ok(Some(retry_after)) => return too_many_attempts_response(retry_after),
+        Ok(None) => {}
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    }
+    
+    let database = db.clone();
+    
+    let user = match hashing_pool.run(move || UserCredential::verify_deleted_with_input(&credential, &database)).await {
+        Ok(Ok(u)) => u,
+        Ok(Err(_)) => {
+            record_login_failure(&req, &throttle_keys, &email, mailer.get_ref(), &db);
+            return ApiError::new(ApiErrorCode::AuthFailed, "Invalid credentials.").error_response();
+        }
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    
+    reset_login_throttle(&throttle_keys, &db);
+    
+    match user.restore_account(&db) {
+        Ok(_) => login_response(&req, &user, None, &db),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+pub async fn login_two_factor(req: HttpRequest, input: Option<web::Json<InputTwoFactorLogin>>, db: web::Data<DBConPool>) -> impl Responder {
+    let input = match input {
+        Some(i) => i,
+        None => return parse_error_response()
+    };
+    
+    match TwoFactorChallenge::verify(&input, &db) {
+        Ok((credential, device_name)) => complete_login(&req, &credential.id, device_name, &db),
+        Err(TwoFactorVerificationError::InvalidCode { user_id }) => {
+            record_security_event(&req, &user_id, SecurityEventType::LoginFailed, Some("two_factor".to_string()), &db);
+            ApiError::new(ApiErrorCode::AuthFailed, "Invalid code.").error_response()
+        }
+        Err(TwoFactorVerificationError::Rejected(e)) => e.error_response()
+    }
+}
+
+pub async fn request_magic_link(input: Option<web::Json<InputMagicLinkRequest>>, mailer: web::Data<SharedMailer>, db: web::Data<DBConPool>) -> impl Responder {
+    let InputMagicLinkRequest { email, device_name } = match input {
+        Some(i) => i.into_inner(),
+        None => return parse_error_response()
+    };
+    
+    // Unknown addresses and accounts that opted out get the same response, so that this cannot tell them apart.
+    match UserCredential::fetch_by_email(&email, &db) {
+        Ok(credential) if credential.magic_link_enabled => match MagicLinkToken::issue(&credential.id, device_name, &db) {
+            Ok(Some(token)) => send_magic_link_mail(mailer.get_ref().clone(), &credential.email, &token),
+            Ok(None) => {}
+            Err(e) => error!("failed to issue magic link token ({}): {:?}", credential.id, e),
+        },
+        _ => {}
+    }
+    
+    HttpResponse::Accepted().finish()
+}
+
+pub async fn login_magic_link(req: HttpRequest, input: Option<web::Json<InputMagicLinkLogin>>, db: web::Data<DBConPool>) -> impl Responder {
+    let input = match input {
+        Some(i) => i,
+        None => return parse_error_response()
+    };
+    
+    // The link stands in for the password only, so an account with 2FA still gets a challenge.
+    match MagicLinkToken::consume(&input.token, &db) {
+        Ok((credential, device_name)) => login_response(&req, &credential, device_name, &db),
+        Err(e) => {
+            if let Ok(user_id) = MagicLinkToken::fetch_user_id(&input.token, &db) {
+                record_security_event(&req, &user_id, SecurityEventType::LoginFailed, Some("magic_link".to_string()), &db);
             }
-        ),
+            e.error_response()
+        }
+    }
+}
+
+pub async fn refresh(req: HttpRequest, input: Option<web::Json<InputRefreshToken>>, db: web::Data<DBConPool>) -> impl Responder {
+    if let Some(input) = input {
+        return match UserRefreshToken::rotate(&input.refresh_token, None, &db) {
+            Ok(t) => HttpResponse::Ok().json(t),
+            Err(e) => e.error_response()
+        };
+    }
+    
+    let refresh_token = match session_cookie::refresh_token(&req) {
+        Some(t) => t,
+        None => return parse_error_response()
+    };
+    
+    if let Err(e) = session_cookie::verify_csrf(req.method(), &req) {
+        return e.error_response();
+    }
+    
+    match UserRefreshToken::rotate(&refresh_token, None, &db) {
+        Ok(t) => session_cookie::token_response(&t),
+        Err(e) => e.error_response()
     }
 }
 
-pub async fn register(new_credential: Option<web::Json<InputUserCredential>>, db: web::Data<DBConPool>) -> impl Responder {
-    let new_credential = match new_credential {
-        Some(c) => c,
+pub async fn register(req: HttpRequest, input: Option<web::Json<InputRegistration>>, mailer: web::Data<SharedMailer>, registration_mode: web::Data<RegistrationMode>, hashing_pool: web::Data<PasswordHashingPool>, db: web::Data<DBConPool>) -> impl Responder {
+    let input = match input {
+        Some(i) => i.into_inner(),
         None => return parse_error_response()
     };
     
-    let result = UserCredential::insert(new_credential.0, &db).map_err(
-        |e| HttpResponse::BadRequest().json(
+    // Every registration is counted, since what this limits is a flood of new accounts from one address.
+    if let Some(throttle_key) = client_ip_address(&req).map(|ip| ThrottleKey::registration_ip(&ip)) {
+        match AuthThrottle::retry_after(std::slice::from_ref(&throttle_key), &db) {
+            Ok(Some(retry_after)) => return too_many_attempts_response(retry_after),
+            Ok(None) => {}
+            Err(_) => return HttpResponse::InternalServerError().finish()
+        }
+        
+        if let Err(e) = AuthThrottle::record_failure(&throttle_key, &db) {
+            error!("failed to record registration attempt: {:?}", e);
+        }
+    }
+    
+    let email = input.credential.email.clone();
+    let mode = *registration_mode.get_ref();
+    
+    let database = db.clone();
+    let result = match hashing_pool.run(move || UserCredential::register(input, mode, &database)).await {
+        Ok(r) => r,
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    };
+    
+    match result {
+        Ok(registration) => {
+            // The account exists at this point, so a failed mail is left to the resend endpoint.
+            match EmailVerificationToken::issue(&registration.id, &email, &db) {
+                Ok(token) => send_verification_mail(mailer.get_ref().clone(), &email, &token),
+                Err(e) => error!("failed to issue verification token ({}): {}", registration.id, e.message),
+            }
+            
+            HttpResponse::Created().json(
+                hashmap! { "approval_pending" => registration.is_pending_approval }
+            )
+        }
+        Err(RegistrationError::InvalidParameter(e)) => HttpResponse::BadRequest().json(
             hashmap! {
                 "error" => ApiError::new_with_detail(ApiErrorCode::InvalidRequest, "Invalid parameter.", e)
             }
-        )
-    );
+        ),
+        Err(RegistrationError::Denied(e)) => e.error_response(),
+        Err(RegistrationError::Failed) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+pub async fn show_registration_mode(registration_mode: web::Data<RegistrationMode>) -> impl Responder {
+    HttpResponse::Ok().json(
+        hashmap! { "registration_mode" => registration_mode.get_ref() }
+    )
+}
+
+pub async fn verify_email(input: Option<web::Json<InputEmailVerification>>, db: web::Data<DBConPool>) -> impl Responder {
+    let input = match input {
+        Some(i) => i,
+        None => return parse_error_response()
+    };
+    
+    match EmailVerificationToken::confirm(&input.token, &db) {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(e) => e.error_response()
+    }
+}
+
+pub async fn resend_verification_email(authorized_user: web::ReqData<AuthorizedUser>, mailer: web::Data<SharedMailer>, db: web::Data<DBConPool>) -> impl Responder {
+    let credential = &authorized_user.credential;
+    
+    if credential.is_email_verified() {
+        return ApiError::new(ApiErrorCode::InvalidRequest, "Email is already verified.").error_response();
+    }
+    
+    match EmailVerificationToken::issue(&credential.id, &credential.email, &db) {
+        Ok(token) => {
+            send_verification_mail(mailer.get_ref().clone(), &credential.email, &token);
+            HttpResponse::Accepted().finish()
+        }
+        Err(e) => e.error_response()
+    }
+}
+
+pub async fn forgot_password(input: Option<web::Json<InputForgotPassword>>, mailer: web::Data<SharedMailer>, db: web::Data<DBConPool>) -> impl Responder {
+    let input = match input {
+        Some(i) => i,
+        None => return parse_error_response()
+    };
+    
+    // Unknown addresses get the same response, so that this cannot tell whether an account exists.
+    if let Ok(credential) = UserCredential::fetch_by_email(&input.email, &db) {
+        match PasswordResetToken::issue(&credential.id, &db) {
+            Ok(Some(token)) => send_password_reset_mail(mailer.get_ref().clone(), &credential.email, &token),
+            Ok(None) => {}
+            Err(e) => error!("failed to issue password reset token ({}): {:?}", credential.id, e),
+        }
+    }
+    
+    HttpResponse::Accepted().finish()
+}
+
+pub async fn reset_password(req: HttpRequest, input: Option<web::Json<InputPasswordReset>>, hashing_pool: web::Data<PasswordHashingPool>, db: web::Data<DBConPool>) -> impl Responder {
+    let input = match input {
+        Some(i) => i,
+        None => return parse_error_response()
+    };
+    
+    let database = db.clone();
+    let result = hashing_pool.run(move || PasswordResetToken::reset(input.into_inner(), &database)).await;
     
     match result {
-        Ok(Some(_)) => HttpResponse::Created().finish(),
-        Err(e) => e,
-        _ => HttpResponse::InternalServerError().finish(),
+        Ok(Ok(user_id)) => {
+            record_security_event(&req, &user_id, SecurityEventType::PasswordReset, None, &db);
+            HttpResponse::NoContent().finish()
+        }
+        Ok(Err(e)) => e.error_response(),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+pub async fn change_password(req: HttpRequest, input: Option<web::Json<InputPasswordChange>>, authorized_user: web::ReqData<AuthorizedUser>, hashing_pool: web::Data<PasswordHashingPool>, db: web::Data<DBConPool>) -> impl Responder {
+    let input = match input {
+        Some(i) => i,
+        None => return parse_error_response()
+    };
+    
+    let (credential, token) = (authorized_user.credential.clone(), authorized_user.token.clone());
+    let database = db.clone();
+    let result = hashing_pool.run(move || credential.change_password(input.into_inner(), &token, &database)).await;
+    
+    match result {
+        Ok(Ok(_)) => {
+            record_security_event(&req, &authorized_user.credential.id, SecurityEventType::PasswordChanged, None, &db);
+            HttpResponse::NoContent().finish()
+        }
+        Ok(Err(e)) => e.error_response(),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+pub async fn request_email_change(req: HttpRequest, input: Option<web::Json<InputEmailChange>>, authorized_user: web::ReqData<AuthorizedUser>, mailer: web::Data<SharedMailer>, hashing_pool: web::Data<PasswordHashingPool>, db: web::Data<DBConPool>) -> impl Responder {
+    let input = match input {
+        Some(i) => i.into_inner(),
+        None => return parse_error_response()
+    };
+    let new_email = input.email.clone();
+    
+    let credential = authorized_user.credential.clone();
+    let database = db.clone();
+    let result = hashing_pool.run(move || EmailChangeToken::issue(&credential, input, &database)).await;
+    
+    match result {
+        Ok(Ok(token)) => {
+            record_security_event(&req, &authorized_user.credential.id, SecurityEventType::EmailChangeRequested, Some(new_email.clone()), &db);
+            mailer::send_in_background(mailer.get_ref().clone(), Mail {
+                to: new_email,
+                subject: "Confirm your new email address".to_string(),
+                body: format!("Open the link below to use this address for your account. It expires in 24 hours.\n\n{}\n", frontend_link("/confirm_email_change", &token)),
+            });
+            HttpResponse::Accepted().finish()
+        }
+        Ok(Err(e)) => e.error_response(),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+pub async fn confirm_email_change(req: HttpRequest, input: Option<web::Json<InputEmailChangeConfirmation>>, mailer: web::Data<SharedMailer>, db: web::Data<DBConPool>) -> impl Responder {
+    let input = match input {
+        Some(i) => i,
+        None => return parse_error_response()
+    };
+    
+    match EmailChangeToken::confirm(&input.token, &db) {
+        Ok(changed_email) => {
+            record_security_event(&req, &changed_email.user_id, SecurityEventType::EmailChanged, None, &db);
+            // The notice lets the owner of the old address notice a takeover.
+            mailer::send_in_background(mailer.get_ref().clone(), Mail {
+                to: changed_email.old_email,
+                subject: "Your email address was changed".to_string(),
+                body: format!("The email address of your account was changed to {}.\nIf you did not do this, reset your password right away.\n", changed_email.new_email),
+            });
+            HttpResponse::NoContent().finish()
+        }
+        Err(e) => e.error_response()
+    }
+}
+
+pub async fn update_magic_link_setting(input: Option<web::Json<InputMagicLinkSetting>>, authorized_user: web::ReqData<AuthorizedUser>, db: web::Data<DBConPool>) -> impl Responder {
+    let input = match input {
+        Some(i) => i,
+        None => return parse_error_response()
+    };
+    
+    match authorized_user.credential.set_magic_link_enabled(input.enabled, &db) {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish()
     }
 }
 
@@ -59,14 +385,136 @@ pub async fn show_me(authorized_user: web::ReqData<AuthorizedUser>) -> impl Resp
     )
 }
 
-pub async fn logout(authorized_user: web::ReqData<AuthorizedUser>, db: web::Data<DBConPool>) -> impl Responder {
-    UserToken::revoke(&authorized_user.token, &db)
-        .map(|_| HttpResponse::NoContent().finish())
-        .map_err(
-            |e| e.error_response()
-        )
+pub async fn logout(req: HttpRequest, authorized_user: web::ReqData<AuthorizedUser>, db: web::Data<DBConPool>) -> impl Responder {
+    if let Err(e) = UserToken::revoke(&authorized_user.token, &db) {
+        return e.error_response();
+    }
+    
+    record_security_event(&req, &authorized_user.credential.id, SecurityEventType::Logout, None, &db);
+    
+    match session_cookie::session_token(&req) {
+        Some(_) => session_cookie::clear_cookies(&mut HttpResponse::NoContent()).finish(),
+        None => HttpResponse::NoContent().finish()
+    }
+}
+
+fn send_verification_mail(mailer: SharedMailer, email: &String, token: &String) {
+    mailer::send_in_background(mailer, Mail {
+        to: email.clone(),
+        subject: "Verify your email address".to_string(),
+        body: format!("Open the link below to verify your email address. It expires in 24 hours.\n\n{}\n", frontend_link("/verify_email", token)),
+    });
+}
+
+fn send_password_reset_mail(mailer: SharedMailer, email: &String, token: &String) {
+    mailer::send_in_background(mailer, Mail {
+        to: email.clone(),
+        subject: "Reset your password".to_string(),
+        body: format!(
+            "Open the link below to choose a new password. It expires in 30 minutes.\nIf you did not ask for this, you can ignore this mail.\n\n{}\n",
+            frontend_link("/reset_password", token)
+        ),
+    });
+}
+
+fn send_magic_link_mail(mailer: SharedMailer, email: &String, token: &String) {
+    mailer::send_in_background(mailer, Mail {
+        to: email.clone(),
+        subject: "Your login link".to_string(),
+        body: format!(
+            "Open the link below to log in. It expires in {} minutes and works only once.\nIf you did not ask for this, you can ignore this mail.\n\n{}\n",
+            MAGIC_LINK_VALIDITY_MINUTES, frontend_link("/login/magic_link", token)
+        ),
+    });
+}
+
+fn frontend_link(path: &str, token: &String) -> url::Url {
+    let mut link = url::Url::parse(&std::env::var("FRONTEND_URL").expect("invalid FRONTEND_URL")).expect("invalid FRONTEND_URL");
+    link.set_path(path);
+    link.query_pairs_mut().append_pair("token", token);
+    
+    link
+}
+
+fn login_throttle_keys(req: &HttpRequest, email: &str) -> Vec<ThrottleKey> {
+    let mut keys = vec![ThrottleKey::login_account(email)];
+    
+    if let Some(ip_address) = client_ip_address(req) {
+        keys.push(ThrottleKey::login_ip(&ip_address));
+    }
+    
+    keys
+}
+
+// Only the account is cleared, so that one valid login does not reset the count of an address spraying passwords.
+fn reset_login_throttle(throttle_keys: &[ThrottleKey], db: &DBConPool) {
+    if let Err(e) = AuthThrottle::reset(&throttle_keys[0], db) {
+        error!("failed to reset login throttle: {:?}", e);
+    }
+}
+
+fn record_login_failure(req: &HttpRequest, throttle_keys: &[ThrottleKey], email: &String, mailer: &SharedMailer, db: &DBConPool) {
+    // Emails without an account are throttled just the same, but there is nobody to log it for or to tell.
+    let credential = UserCredential::fetch_by_email(email, db).ok();
+    
+    if let Some(c) = &credential {
+        record_security_event(req, &c.id, SecurityEventType::LoginFailed, None, db);
+    }
+    
+    for (i, throttle_key) in throttle_keys.iter().enumerate() {
+        match AuthThrottle::record_failure(throttle_key, db) {
+            Ok(true) if i == 0 => {
+                if let Some(c) = &credential {
+                    send_lockout_mail(mailer.clone(), &c.email);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => error!("failed to record login failure: {:?}", e),
+        }
+    }
+}
+
+fn send_lockout_mail(mailer: SharedMailer, email: &String) {
+    mailer::send_in_background(mailer, Mail {
+        to: email.clone(),
+        subject: "Sign-in to your account was paused".to_string(),
+        body: "There were several failed attempts to sign in to your account, so sign-in is paused for a while.\n\
+            If this was not you, consider changing your password and enabling two-factor authentication.\n".to_string(),
+    });
+}
+
+fn login_response(req: &HttpRequest, credential: &UserCredential, device_name: Option<String>, db: &DBConPool) -> HttpResponse {
+    if !credential.is_approved() {
+        return ApiError::new(ApiErrorCode::AccountPendingApproval, "Account is waiting for approval.").error_response();
+    }
+    
+    if !credential.is_two_factor_enabled() {
+        return complete_login(req, &credential.id, device_name, db);
+    }
+    
+    match TwoFactorChallenge::issue(&credential.id, device_name, db) {
+        Ok(challenge) => HttpResponse::Ok().json(
+            hashmap! { "two_factor_challenge" => challenge }
+        ),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+fn complete_login(req: &HttpRequest, user_id: &String, device_name: Option<String>, db: &DBConPool) -> HttpResponse {
+    if let Err(e) = User::reactivate(user_id, db) {
+        error!("failed to reactivate user ({}): {:?}", user_id, e);
+    }
+    
+    let issued_token = issue_user_token(user_id, &session_metadata(req, device_name), db);
+    record_security_event(req, user_id, SecurityEventType::LoginSucceeded, None, db);
+    
+    if session_cookie::is_requested(req) {
+        return session_cookie::token_response(&issued_token);
+    }
+    
+    HttpResponse::Ok().json(issued_token)
 }
 
-fn issue_user_token(user_id: &String, db: &DBConPool) -> String {
-    UserToken::issue(user_id, &db).expect("Failed to issue token")
+fn issue_user_token(user_id: &String, metadata: &SessionMetadata, db: &DBConPool) -> IssuedUserToken {
+    UserToken::issue(user_id, metadata, &db).expect("Failed to issue token")
 }
\ No newline at end of file
@@ -0,0 +1,53 @@
+use actix_web::{HttpResponse, Responder, web};
+use actix_web::http::header;
+use maplit::hashmap;
+
+use crate::controllers::invalid_uuid_response;
+use crate::DBConPool;
+use crate::models::error::{ApiError, ApiErrorCode};
+use crate::models::user_data_export::UserDataExport;
+use crate::services::data_export;
+use crate::services::token_authentication::AuthorizedUser;
+
+pub async fn create(authorized_user: web::ReqData<AuthorizedUser>, db: web::Data<DBConPool>) -> impl Responder {
+    match UserDataExport::request(&authorized_user.credential.id, &db) {
+        Ok(export) => {
+            data_export::spawn(export.export.clone(), db.get_ref().clone());
+            
+            HttpResponse::Accepted().json(
+                hashmap! { "export" => export }
+            )
+        }
+        Err(e) => e.error_response()
+    }
+}
+
+pub async fn show(export_id: Option<web::Path<uuid::Uuid>>, authorized_user: web::ReqData<AuthorizedUser>, db: web::Data<DBConPool>) -> impl Responder {
+    let export_id = match export_id {
+        None => return invalid_uuid_response(),
+        Some(e) => e
+    };
+    
+    match UserDataExport::fetch_by_id(&export_id.to_string(), &authorized_user.credential.id, &db) {
+        Ok(export) => HttpResponse::Ok().json(
+            hashmap! { "export" => export }
+        ),
+        Err(diesel::NotFound) => ApiError::new(ApiErrorCode::NotFound, "Export does not exist.").error_response(),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+pub async fn download(download_token: web::Path<String>, db: web::Data<DBConPool>) -> impl Responder {
+    let export = match UserDataExport::fetch_by_download_token(&download_token, &db) {
+        Ok(e) => e,
+        Err(_) => return ApiError::new(ApiErrorCode::NotFound, "Export does not exist or has expired.").error_response()
+    };
+    
+    match web::block(move || data_export::read_archive(&export)).await {
+        Ok(archive) => HttpResponse::Ok()
+            .content_type("application/zip")
+            .header(header::CONTENT_DISPOSITION, "attachment; filename=\"export.zip\"")
+            .body(archive),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
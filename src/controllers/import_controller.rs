@@ -0,0 +1,56 @@
+use actix_web::{HttpResponse, Responder, web};
+use maplit::hashmap;
+
+use crate::controllers::{email_not_verified_response, invalid_uuid_response};
+use crate::DBConPool;
+use crate::models::error::{ApiError, ApiErrorCode};
+use crate::models::post_import::PostImport;
+use crate::services::post_import;
+use crate::services::token_authentication::AuthorizedUser;
+
+#[derive(Deserialize)]
+pub struct ImportQuery {
+    format: String,
+}
+
+pub async fn create(query: web::Query<ImportQuery>, payload: web::Bytes, authorized_user: web::ReqData<AuthorizedUser>, db: web::Data<DBConPool>) -> impl Responder {
+    let user = match &authorized_user.user {
+        Some(u) => u,
+        None => return ApiError::new(ApiErrorCode::NotFound, "Create user first.").error_response()
+    };
+    
+    if !authorized_user.credential.is_email_verified() {
+        return email_not_verified_response();
+    }
+    
+    if !PostImport::is_supported_format(&query.format) {
+        return ApiError::new(ApiErrorCode::InvalidRequest, "Unsupported import format.").error_response();
+    }
+    
+    match PostImport::request(&user.id, &query.format, &db) {
+        Ok(import) => {
+            post_import::spawn(import.clone(), user.clone(), payload, db.get_ref().clone());
+            
+            HttpResponse::Accepted().json(
+                hashmap! { "import" => import }
+            )
+        }
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+pub async fn show(import_id: Option<web::Path<uuid::Uuid>>, authorized_user: web::ReqData<AuthorizedUser>, db: web::Data<DBConPool>) -> impl Responder {
+    let import_id = match import_id {
+        None => return invalid_uuid_response(),
+        Some(i) => i
+    };
+    
+    let import = PostImport::fetch_by_id(&import_id.to_string(), &authorized_user.credential.id, &db)
+        .and_then(|i| i.with_errors(&db));
+    
+    match import {
+        Ok(i) => HttpResponse::Ok().json(i),
+        Err(diesel::NotFound) => ApiError::new(ApiErrorCode::NotFound, "Import does not exist.").error_response(),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
@@ -0,0 +1,59 @@
+use actix_web::{HttpResponse, Responder, web};
+use maplit::hashmap;
+
+use crate::controllers::{email_not_verified_response, invalid_uuid_response, parse_error_response};
+use crate::DBConPool;
+use crate::models::error::{ApiError, ApiErrorCode};
+use crate::models::invite_code::*;
+use crate::models::registration_mode::RegistrationMode;
+use crate::services::token_authentication::AuthorizedUser;
+
+pub async fn index(authorized_user: web::ReqData<AuthorizedUser>, db: web::Data<DBConPool>) -> impl Responder {
+    match InviteCode::fetch_list_by_inviter(&authorized_user.credential.id, &db) {
+        Ok(invite_codes) => HttpResponse::Ok().json(
+            hashmap! { "invite_codes" => invite_codes }
+        ),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+pub async fn create(input: Option<web::Json<InputInviteCode>>, authorized_user: web::ReqData<AuthorizedUser>, registration_mode: web::Data<RegistrationMode>, db: web::Data<DBConPool>) -> impl Responder {
+    let input = match input {
+        Some(i) => i,
+        None => return parse_error_response()
+    };
+    
+    if *registration_mode.get_ref() == RegistrationMode::Closed {
+        return ApiError::new(ApiErrorCode::RegistrationClosed, "Registration is closed.").error_response();
+    }
+    if !authorized_user.credential.is_email_verified() {
+        return email_not_verified_response();
+    }
+    
+    match InviteCode::issue(&authorized_user.credential, input.0, &db) {
+        Ok(c) => HttpResponse::Created().json(
+            hashmap! { "invite_code" => c }
+        ),
+        Err(InviteCodeIssueError::InvalidParameter(e)) => HttpResponse::BadRequest().json(
+            hashmap! { "error" => ApiError::new_with_detail(ApiErrorCode::InvalidRequest, "Invalid parameter.", e) }
+        ),
+        Err(InviteCodeIssueError::QuotaExceeded) => ApiError::new(ApiErrorCode::RateLimited, "Invite code limit reached. Try again later.").error_response(),
+        Err(InviteCodeIssueError::Failed) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+pub async fn destroy(code_id: Option<web::Path<uuid::Uuid>>, authorized_user: web::ReqData<AuthorizedUser>, db: web::Data<DBConPool>) -> impl Responder {
+    let code_id = match code_id {
+        None => return invalid_uuid_response(),
+        Some(c) => c
+    };
+    
+    let result = InviteCode::fetch_by_id(&code_id.to_string(), &authorized_user.credential.id, &db)
+        .and_then(|c| c.revoke(&db));
+    
+    match result {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(diesel::NotFound) => ApiError::new(ApiErrorCode::NotFound, "Invite code not found.").error_response(),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
\ No newline at end of file
@@ -0,0 +1,213 @@
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+use maplit::hashmap;
+
+use crate::controllers::{invalid_uuid_response, parse_error_response, record_security_event, session_metadata};
+use crate::DBConPool;
+use crate::models::error::{ApiError, ApiErrorCode, OAuthError};
+use crate::models::oauth_app::*;
+use crate::models::oauth_authorization_code::*;
+use crate::models::security_event::SecurityEventType;
+use crate::models::token_scope::{join_scope_list, TokenScope};
+use crate::models::user_refresh_token::UserRefreshToken;
+use crate::models::user_token::{ACCESS_TOKEN_VALIDITY_MINUTES, IssuedUserToken};
+use crate::services::token_authentication::AuthorizedUser;
+
+#[derive(Serialize)]
+struct TokenResponse {
+    access_token: String,
+    token_type: &'static str,
+    expires_in: i64,
+    refresh_token: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scope: Option<String>,
+}
+
+impl TokenResponse {
+    fn new(issued_token: IssuedUserToken, scopes: Option<&[TokenScope]>) -> Self {
+        Self {
+            access_token: issued_token.token,
+            token_type: "Bearer",
+            expires_in: ACCESS_TOKEN_VALIDITY_MINUTES * 60,
+            refresh_token: issued_token.refresh_token,
+            scope: scopes.map(join_scope_list),
+        }
+    }
+}
+
+pub async fn apps_index(authorized_user: web::ReqData<AuthorizedUser>, db: web::Data<DBConPool>) -> impl Responder {
+    match OAuthApp::fetch_list_by_owner(&authorized_user.credential.id, &db) {
+        Ok(apps) => HttpResponse::Ok().json(
+            hashmap! { "apps" => apps }
+        ),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+pub async fn apps_create(input: Option<web::Json<InputOAuthApp>>, authorized_user: web::ReqData<AuthorizedUser>, db: web::Data<DBConPool>) -> impl Responder {
+    let input = match input {
+        Some(i) => i,
+        None => return parse_error_response()
+    };
+    
+    let result = OAuthApp::register(&authorized_user.credential.id, input.0, &db).map_err(
+        |e| HttpResponse::BadRequest().json(
+            hashmap! { "error" => ApiError::new_with_detail(ApiErrorCode::InvalidRequest, "Invalid parameter.", e) }
+        )
+    );
+    
+    match result {
+        Ok(Some(app)) => HttpResponse::Created().json(
+            hashmap! { "app" => app }
+        ),
+        Err(e) => e,
+        _ => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+pub async fn apps_destroy(app_id: Option<web::Path<uuid::Uuid>>, authorized_user: web::ReqData<AuthorizedUser>, db: web::Data<DBConPool>) -> impl Responder {
+    let app_id = match app_id {
+        None => return invalid_uuid_response(),
+        Some(a) => a
+    };
+    
+    let result = OAuthApp::fetch_owned(&app_id.to_string(), &authorized_user.credential.id, &db)
+        .and_then(|a| a.delete(&db));
+    
+    match result {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(diesel::NotFound) => ApiError::new(ApiErrorCode::NotFound, "App not found.").error_response(),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+pub async fn show_authorization_request(request: web::Query<InputAuthorizationRequest>, db: web::Data<DBConPool>) -> impl Responder {
+    match request.validate_for_app(&db) {
+        Ok((app, scopes)) => HttpResponse::Ok().json(
+            serde_json::json!({
+                "app": { "client_id": app.id, "name": app.name },
+                "scopes": scopes.iter().map(|s| s.as_str()).collect::<Vec<&str>>(),
+            })
+        ),
+        Err(e) => e.error_response()
+    }
+}
+
+pub async fn authorize(req: HttpRequest, request: Option<web::Json<InputAuthorizationRequest>>, authorized_user: web::ReqData<AuthorizedUser>, db: web::Data<DBConPool>) -> impl Responder {
+    let request = match request {
+        Some(r) => r,
+        None => return parse_error_response()
+    };
+    
+    let (app, scopes) = match request.validate_for_app(&db) {
+        Ok(r) => r,
+        Err(e) => return e.error_response()
+    };
+    
+    let mut params = vec![];
+    
+    if request.approved {
+        match OAuthAuthorizationCode::issue(&request, &scopes, &authorized_user.credential.id, &db) {
+            Ok(code) => {
+                record_security_event(&req, &authorized_user.credential.id, SecurityEventType::TokenIssued, Some(format!("oauth_app:{}", app.id)), &db);
+                params.push(("code", code));
+            }
+            Err(_) => return HttpResponse::InternalServerError().finish()
+        }
+    } else {
+        params.push(("error", "access_denied".to_string()));
+    }
+    
+    if let Some(state) = &request.state {
+        params.push(("state", state.clone()));
+    }
+    
+    // `validate_for_app` already checked that the redirect URI is registered and parses.
+    let mut redirect_uri = url::Url::parse(&request.redirect_uri).expect("registered redirect_uri is invalid");
+    redirect_uri.query_pairs_mut().extend_pairs(params);
+    
+    HttpResponse::Ok().json(
+        hashmap! { "redirect_uri" => redirect_uri.to_string() }
+    )
+}
+
+pub async fn token(req: HttpRequest, request: Option<web::Form<InputTokenRequest>>, db: web::Data<DBConPool>) -> impl Responder {
+    let request = match request {
+        Some(r) => r,
+        None => return OAuthError::new("invalid_request", "Failed to parse request.").error_response()
+    };
+    
+    let app = match authenticate_client(request.client_id.as_ref(), request.client_secret.as_ref(), &db) {
+        Ok(a) => a,
+        Err(e) => return e.error_response()
+    };
+    
+    let result = match request.grant_type.as_str() {
+        "authorization_code" => OAuthAuthorizationCode::exchange(&app, &request, &session_metadata(&req, None), &db)
+            .map(|(t, scopes)| TokenResponse::new(t, Some(scopes.as_slice()))),
+        "refresh_token" => match &request.refresh_token {
+            Some(refresh_token) => UserRefreshToken::rotate(refresh_token, Some(&app.id), &db)
+                .map(|t| TokenResponse::new(t, None))
+                .map_err(|_| OAuthError::new("invalid_grant", "Refresh token is invalid or expired.")),
+            None => Err(OAuthError::new("invalid_request", "refresh_token is required.")),
+        },
+        _ => Err(OAuthError::new("unsupported_grant_type", "Only authorization_code and refresh_token are supported.")),
+    };
+    
+    match result {
+        Ok(t) => HttpResponse::Ok().header("Cache-Control", "no-store").json(t),
+        Err(e) => e.error_response()
+    }
+}
+
+pub async fn revoke(request: Option<web::Form<InputRevocationRequest>>, db: web::Data<DBConPool>) -> impl Responder {
+    let request = match request {
+        Some(r) => r,
+        None => return OAuthError::new("invalid_request", "Failed to parse request.").error_response()
+    };
+    
+    let app = match authenticate_client(request.client_id.as_ref(), request.client_secret.as_ref(), &db) {
+        Ok(a) => a,
+        Err(e) => return e.error_response()
+    };
+    
+    match app.revoke_token(&request.token, &db) {
+        Ok(_) => HttpResponse::Ok().finish(),
+        Err(_) => OAuthError::new("server_error", "Failed to revoke token.").error_response()
+    }
+}
+
+pub async fn authorizations_index(authorized_user: web::ReqData<AuthorizedUser>, db: web::Data<DBConPool>) -> impl Responder {
+    match OAuthApp::fetch_authorized_list(&authorized_user.credential.id, &db) {
+        Ok(apps) => HttpResponse::Ok().json(
+            hashmap! { "apps" => apps }
+        ),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+pub async fn authorizations_destroy(req: HttpRequest, app_id: Option<web::Path<uuid::Uuid>>, authorized_user: web::ReqData<AuthorizedUser>, db: web::Data<DBConPool>) -> impl Responder {
+    let app_id = match app_id {
+        None => return invalid_uuid_response(),
+        Some(a) => a
+    };
+    
+    match OAuthApp::revoke_authorization(&app_id.to_string(), &authorized_user.credential.id, &db) {
+        Ok(_) => {
+            record_security_event(&req, &authorized_user.credential.id, SecurityEventType::TokenRevoked, Some(format!("oauth_app:{}", app_id)), &db);
+            HttpResponse::NoContent().finish()
+        }
+        Err(e) => e.error_response()
+    }
+}
+
+fn authenticate_client(client_id: Option<&String>, client_secret: Option<&String>, db: &DBConPool) -> Result<OAuthApp, OAuthError> {
+    let app = client_id
+        .and_then(|id| OAuthApp::fetch_by_id(id, db).ok())
+        .ok_or_else(|| OAuthError::new("invalid_client", "Unknown client."))?;
+    
+    if !app.verify_client_secret(client_secret) {
+        return Err(OAuthError::new("invalid_client", "Client authentication failed."));
+    }
+    
+    Ok(app)
+}
@@ -0,0 +1,63 @@
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+use maplit::hashmap;
+
+use crate::controllers::{invalid_uuid_response, parse_error_response, record_security_event};
+use crate::DBConPool;
+use crate::models::error::{ApiError, ApiErrorCode};
+use crate::models::personal_access_token::*;
+use crate::models::security_event::SecurityEventType;
+use crate::services::token_authentication::AuthorizedUser;
+
+pub async fn index(authorized_user: web::ReqData<AuthorizedUser>, db: web::Data<DBConPool>) -> impl Responder {
+    match PersonalAccessToken::fetch_list_by_user(&authorized_user.credential.id, &db) {
+        Ok(tokens) => HttpResponse::Ok().json(
+            hashmap! { "tokens" => tokens }
+        ),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+pub async fn create(req: HttpRequest, input: Option<web::Json<InputPersonalAccessToken>>, authorized_user: web::ReqData<AuthorizedUser>, db: web::Data<DBConPool>) -> impl Responder {
+    let input = match input {
+        Some(i) => i,
+        None => return parse_error_response()
+    };
+    
+    let token_name = input.name.clone();
+    
+    let result = PersonalAccessToken::issue(&authorized_user.credential.id, input.0, &db).map_err(
+        |e| HttpResponse::BadRequest().json(
+            hashmap! { "error" => ApiError::new_with_detail(ApiErrorCode::InvalidRequest, "Invalid parameter.", e) }
+        )
+    );
+    
+    match result {
+        Ok(Some(t)) => {
+            record_security_event(&req, &authorized_user.credential.id, SecurityEventType::TokenIssued, Some(format!("personal_access_token:{}", token_name)), &db);
+            HttpResponse::Created().json(
+                hashmap! { "token" => t }
+            )
+        }
+        Err(e) => e,
+        _ => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+pub async fn destroy(req: HttpRequest, token_id: Option<web::Path<uuid::Uuid>>, authorized_user: web::ReqData<AuthorizedUser>, db: web::Data<DBConPool>) -> impl Responder {
+    let token_id = match token_id {
+        None => return invalid_uuid_response(),
+        Some(t) => t
+    };
+    
+    let result = PersonalAccessToken::fetch_by_id(&token_id.to_string(), &authorized_user.credential.id, &db)
+        .and_then(|t| t.revoke(&db).map(|_| t));
+    
+    match result {
+        Ok(t) => {
+            record_security_event(&req, &authorized_user.credential.id, SecurityEventType::TokenRevoked, Some(format!("personal_access_token:{}", t.name)), &db);
+            HttpResponse::NoContent().finish()
+        }
+        Err(diesel::NotFound) => ApiError::new(ApiErrorCode::NotFound, "Token not found.").error_response(),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
\ No newline at end of file
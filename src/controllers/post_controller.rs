@@ -1,7 +1,8 @@
-use actix_web::{HttpResponse, Responder, web};
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+use actix_web::http::header::ETag;
 use maplit::hashmap;
 
-use crate::controllers::{invalid_uuid_response, parse_error_response};
+use crate::controllers::{deactivated_user_response, email_not_verified_response, invalid_uuid_response, is_not_modified, not_modified_response, parse_error_response};
 use crate::DBConPool;
 use crate::models::error::{ApiError, ApiErrorCode};
 use crate::models::post::{InputPost, Post};
@@ -18,7 +19,7 @@ pub async fn index() -> impl Responder {
     "index"
 }
 
-pub async fn show(post_id: Option<web::Path<uuid::Uuid>>, db: web::Data<DBConPool>) -> impl Responder {
+pub async fn show(post_id: Option<web::Path<uuid::Uuid>>, request: HttpRequest, db: web::Data<DBConPool>) -> impl Responder {
     let post_id = match post_id {
         None => return invalid_uuid_response(),
         Some(u) => u
@@ -29,7 +30,18 @@ pub async fn show(post_id: Option<web::Path<uuid::Uuid>>, db: web::Data<DBConPoo
     match post {
         Ok(p) => {
             let u = User::fetch_by_id(&p.author_id, &db).expect("User does not exists but `id` found in Post.");
-            HttpResponse::Ok().json(p.with_user(u.filter_for_response()))
+            if u.is_deactivated() {
+                return ApiError::new(ApiErrorCode::NotFound, "Post does not exist.").error_response();
+            }
+            
+            let post = p.with_user(u.filter_for_response());
+            let entity_tag = post.entity_tag();
+            
+            if is_not_modified(&request, &entity_tag) {
+                return not_modified_response(entity_tag);
+            }
+            
+            HttpResponse::Ok().set(ETag(entity_tag)).json(post)
         }
         Err(diesel::NotFound) => HttpResponse::NotFound().json(
             hashmap! { "error" => ApiError::new(ApiErrorCode::NotFound, "Post does not exist.") }
@@ -44,6 +56,10 @@ pub async fn create(new_post: Option<web::Json<InputPost>>, authorized_user: web
         None => return ApiError::new(ApiErrorCode::NotFound, "Create user first.").error_response()
     };
     
+    if !authorized_user.credential.is_email_verified() {
+        return email_not_verified_response();
+    }
+    
     let new_post = match new_post {
         None => return parse_error_response(),
         Some(p) => p
@@ -81,6 +97,17 @@ pub async fn users_index(user_id: Option<web::Path<uuid::Uuid>>, pagination: web
     };
     
     let user = match User::fetch_by_id(&user_id.to_string(), &db) {
+        Ok(u) if u.is_deactivated() => return deactivated_user_response(),
+        Ok(u) => u,
+        Err(_) => return ApiError::new(ApiErrorCode::NotFound, "User not found").error_response()
+    };
+    
+    response_fetch_posts_by_user(&db, &user, pagination)
+}
+
+pub async fn users_index_by_name(id_name: web::Path<String>, pagination: web::Query<PostIdPagination>, db: web::Data<DBConPool>) -> impl Responder {
+    let user = match User::fetch_by_id_name(&id_name, &db) {
+        Ok(u) if u.is_deactivated() => return deactivated_user_response(),
         Ok(u) => u,
         Err(_) => return ApiError::new(ApiErrorCode::NotFound, "User not found").error_response()
     };
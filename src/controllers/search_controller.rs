@@ -0,0 +1,64 @@
+use actix_web::{HttpResponse, Responder, web};
+use maplit::hashmap;
+
+use crate::DBConPool;
+use crate::models::error::{ApiError, ApiErrorCode};
+use crate::models::post::Post;
+use crate::models::post_search::PostSearchQuery;
+use crate::models::user::{FilteredUser, User};
+use crate::services::token_authentication::AuthorizedUser;
+
+const MAX_SEARCH_PAGE: i64 = 100;
+
+#[derive(Deserialize)]
+pub struct SearchQuery {
+    q: String,
+    page: Option<i64>,
+}
+
+pub async fn users(query: web::Query<SearchQuery>, authorized_user: Option<web::ReqData<AuthorizedUser>>, db: web::Data<DBConPool>) -> impl Responder {
+    if query.q.trim().is_empty() {
+        return ApiError::new(ApiErrorCode::InvalidRequest, "Search query is empty.").error_response();
+    }
+    let page = match search_page(&query) {
+        Ok(p) => p,
+        Err(e) => return e.error_response()
+    };
+    let viewer_id = authorized_user.as_ref().map(|u| &u.credential.id);
+    
+    match User::search(&query.q.trim().to_string(), page, viewer_id, &db) {
+        Ok(users) => HttpResponse::Ok().json(
+            hashmap! {
+                "users" => users.iter().map(|u| u.filter_for_response()).collect::<Vec<FilteredUser>>()
+            }
+        ),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+pub async fn posts(query: web::Query<SearchQuery>, db: web::Data<DBConPool>) -> impl Responder {
+    let search_query = match PostSearchQuery::parse(&query.q) {
+        Ok(q) => q,
+        Err(e) => return e.error_response()
+    };
+    
+    let page = match search_page(&query) {
+        Ok(p) => p,
+        Err(e) => return e.error_response()
+    };
+    
+    match Post::search(&search_query, page, &db) {
+        Ok(posts) => HttpResponse::Ok().json(
+            posts.wrap_tagged()
+        ),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+// Bounds the offset the page turns into, which would overflow for huge page numbers.
+fn search_page(query: &SearchQuery) -> Result<i64, ApiError> {
+    match query.page.unwrap_or(0).max(0) {
+        p if p <= MAX_SEARCH_PAGE => Ok(p),
+        _ => Err(ApiError::new(ApiErrorCode::InvalidRequest, "Invalid page.")),
+    }
+}
\ No newline at end of file
@@ -0,0 +1,15 @@
+use actix_web::{HttpResponse, Responder, web};
+use maplit::hashmap;
+
+use crate::DBConPool;
+use crate::models::security_event::*;
+use crate::services::token_authentication::AuthorizedUser;
+
+pub async fn index(authorized_user: web::ReqData<AuthorizedUser>, pagination: web::Query<SecurityEventIdPagination>, db: web::Data<DBConPool>) -> impl Responder {
+    match SecurityEvent::fetch_list_by_user(&authorized_user.credential.id, pagination.oldest_event_id, &db) {
+        Ok(events) => HttpResponse::Ok().json(
+            hashmap! { "security_events" => events }
+        ),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
\ No newline at end of file
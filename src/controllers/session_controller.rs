@@ -0,0 +1,48 @@
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+use maplit::hashmap;
+
+use crate::controllers::{invalid_uuid_response, record_security_event};
+use crate::DBConPool;
+use crate::models::security_event::SecurityEventType;
+use crate::models::user_session::UserSession;
+use crate::models::user_token::UserToken;
+use crate::services::token_authentication::AuthorizedUser;
+
+pub async fn index(authorized_user: web::ReqData<AuthorizedUser>, db: web::Data<DBConPool>) -> impl Responder {
+    match UserSession::fetch_list_by_user(&authorized_user.credential.id, &db) {
+        Ok(sessions) => HttpResponse::Ok().json(
+            hashmap! {
+                "sessions" => sessions.into_iter().map(|s| s.for_response(authorized_user.session_id.as_ref())).collect::<Vec<_>>()
+            }
+        ),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+pub async fn destroy(req: HttpRequest, session_id: Option<web::Path<uuid::Uuid>>, authorized_user: web::ReqData<AuthorizedUser>, db: web::Data<DBConPool>) -> impl Responder {
+    let session_id = match session_id {
+        None => return invalid_uuid_response(),
+        Some(s) => s
+    };
+    
+    let result = UserSession::fetch_by_id(&session_id.to_string(), &authorized_user.credential.id, &db)
+        .and_then(|s| s.revoke(&db));
+    
+    match result {
+        Ok(_) => {
+            record_security_event(&req, &authorized_user.credential.id, SecurityEventType::SessionRevoked, Some(session_id.to_string()), &db);
+            HttpResponse::NoContent().finish()
+        }
+        Err(e) => e.error_response()
+    }
+}
+
+pub async fn destroy_others(req: HttpRequest, authorized_user: web::ReqData<AuthorizedUser>, db: web::Data<DBConPool>) -> impl Responder {
+    match UserToken::revoke_others(&authorized_user.credential.id, &authorized_user.token, &db) {
+        Ok(_) => {
+            record_security_event(&req, &authorized_user.credential.id, SecurityEventType::SessionRevoked, Some("other_sessions".to_string()), &db);
+            HttpResponse::NoContent().finish()
+        }
+        Err(e) => e.error_response()
+    }
+}
\ No newline at end of file
@@ -0,0 +1,86 @@
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+use maplit::hashmap;
+
+use crate::controllers::{parse_error_response, record_security_event};
+use crate::DBConPool;
+use crate::models::security_event::SecurityEventType;
+use crate::models::user_credential::{InputPassword, InputPasswordAndCode, InputTwoFactorCode};
+use crate::services::password_hashing::PasswordHashingPool;
+use crate::services::token_authentication::AuthorizedUser;
+
+pub async fn enroll(input: Option<web::Json<InputPassword>>, authorized_user: web::ReqData<AuthorizedUser>, hashing_pool: web::Data<PasswordHashingPool>, db: web::Data<DBConPool>) -> impl Responder {
+    let input = match input {
+        Some(i) => i,
+        None => return parse_error_response()
+    };
+    
+    let credential = authorized_user.credential.clone();
+    let result = hashing_pool.run(move || credential.start_two_factor_enrollment(input.into_inner(), &db)).await;
+    
+    match result {
+        Ok(Ok(enrollment)) => HttpResponse::Ok().json(
+            hashmap! { "two_factor" => enrollment }
+        ),
+        Ok(Err(e)) => e.error_response(),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+pub async fn confirm(req: HttpRequest, input: Option<web::Json<InputTwoFactorCode>>, authorized_user: web::ReqData<AuthorizedUser>, db: web::Data<DBConPool>) -> impl Responder {
+    let input = match input {
+        Some(i) => i,
+        None => return parse_error_response()
+    };
+    
+    match authorized_user.credential.enable_two_factor(input.into_inner(), &db) {
+        Ok(codes) => {
+            record_security_event(&req, &authorized_user.credential.id, SecurityEventType::TwoFactorEnabled, None, &db);
+            HttpResponse::Ok().json(
+                hashmap! { "recovery_codes" => codes }
+            )
+        }
+        Err(e) => e.error_response()
+    }
+}
+
+pub async fn disable(req: HttpRequest, input: Option<web::Json<InputPasswordAndCode>>, authorized_user: web::ReqData<AuthorizedUser>, hashing_pool: web::Data<PasswordHashingPool>, db: web::Data<DBConPool>) -> impl Responder {
+    let input = match input {
+        Some(i) => i,
+        None => return parse_error_response()
+    };
+    
+    let credential = authorized_user.credential.clone();
+    let database = db.clone();
+    let result = hashing_pool.run(move || credential.disable_two_factor(input.into_inner(), &database)).await;
+    
+    match result {
+        Ok(Ok(_)) => {
+            record_security_event(&req, &authorized_user.credential.id, SecurityEventType::TwoFactorDisabled, None, &db);
+            HttpResponse::NoContent().finish()
+        }
+        Ok(Err(e)) => e.error_response(),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+pub async fn regenerate_recovery_codes(req: HttpRequest, input: Option<web::Json<InputPasswordAndCode>>, authorized_user: web::ReqData<AuthorizedUser>, hashing_pool: web::Data<PasswordHashingPool>, db: web::Data<DBConPool>) -> impl Responder {
+    let input = match input {
+        Some(i) => i,
+        None => return parse_error_response()
+    };
+    
+    let credential = authorized_user.credential.clone();
+    let database = db.clone();
+    let result = hashing_pool.run(move || credential.regenerate_recovery_codes(input.into_inner(), &database)).await;
+    
+    match result {
+        Ok(Ok(codes)) => {
+            record_security_event(&req, &authorized_user.credential.id, SecurityEventType::RecoveryCodesRegenerated, None, &db);
+            HttpResponse::Ok().json(
+                hashmap! { "recovery_codes" => codes }
+            )
+        }
+        Ok(Err(e)) => e.error_response(),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
\ No newline at end of file
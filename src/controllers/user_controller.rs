@@ -1,29 +1,28 @@
-use actix_web::{HttpResponse, Responder, web};
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+use actix_web::http::header::ETag;
 use maplit::hashmap;
 
-use crate::controllers::{invalid_uuid_response, is_created_user, parse_error_response};
+use crate::controllers::{deactivated_user_response, has_if_match_tags, invalid_uuid_response, is_created_user, is_not_modified, is_precondition_failed, not_modified_response, parse_error_response, precondition_failed_response};
 use crate::DBConPool;
 use crate::models::error::*;
+use crate::models::personal_access_token::PersonalAccessToken;
 use crate::models::user::*;
+use crate::models::user_credential::InputPassword;
+use crate::models::user_token::UserToken;
+use crate::services::password_hashing::PasswordHashingPool;
 use crate::services::token_authentication::AuthorizedUser;
 
-pub async fn show(user_id: Option<web::Path<uuid::Uuid>>, db: web::Data<DBConPool>) -> impl Responder {
+pub async fn show(user_id: Option<web::Path<uuid::Uuid>>, request: HttpRequest, db: web::Data<DBConPool>) -> impl Responder {
     let user_id = match user_id {
         None => return invalid_uuid_response(),
         Some(u) => u
     };
     
-    let user = User::fetch_by_id(&user_id.to_string(), &db);
-    
-    match user {
-        Ok(user) => HttpResponse::Ok().json(
-            hashmap! { "user" => user.filter_for_response() }
-        ),
-        Err(diesel::NotFound) => HttpResponse::NotFound().json(
-            hashmap! { "error" => ApiError::new(ApiErrorCode::NotFound, "User does not exist.") }
-        ),
-        _ => HttpResponse::InternalServerError().finish()
-    }
+    response_fetch_user(&request, User::fetch_by_id(&user_id.to_string(), &db))
+}
+
+pub async fn show_by_name(id_name: web::Path<String>, request: HttpRequest, db: web::Data<DBConPool>) -> impl Responder {
+    response_fetch_user(&request, User::fetch_by_id_name(&id_name, &db))
 }
 
 pub async fn create(new_user: Option<web::Json<InputUser>>, authorized_user: Option<web::ReqData<AuthorizedUser>>, db: web::Data<DBConPool>) -> impl Responder {
@@ -63,9 +62,14 @@ pub async fn create(new_user: Option<web::Json<InputUser>>, authorized_user: Opt
     }
 }
 
-pub async fn update_me(new_user: Option<web::Json<InputPatchUser>>, authorized_user: web::ReqData<AuthorizedUser>, db: web::Data<DBConPool>) -> impl Responder {
-    if !is_created_user(&authorized_user) {
-        return ApiError::new(ApiErrorCode::NotFound, "Create user first.").error_response();
+pub async fn update_me(new_user: Option<web::Json<InputPatchUser>>, request: HttpRequest, authorized_user: web::ReqData<AuthorizedUser>, db: web::Data<DBConPool>) -> impl Responder {
+    let user = match &authorized_user.user {
+        Some(u) => u,
+        None => return ApiError::new(ApiErrorCode::NotFound, "Create user first.").error_response()
+    };
+    
+    if is_precondition_failed(&request, &user.entity_tag()) {
+        return precondition_failed_response();
     }
     
     let new_user = match new_user {
@@ -73,22 +77,111 @@ pub async fn update_me(new_user: Option<web::Json<InputPatchUser>>, authorized_u
         Some(u) => u
     };
     
-    match User::update(new_user.0, &authorized_user.credential.id, &db) {
-        Ok(u) => {
-            HttpResponse::Ok().json(
-                hashmap! { "user" => u.filter_for_response() }
-            )
-        }
+    let expected_version = has_if_match_tags(&request).then(|| user.version);
+    
+    match User::update(new_user.0, &authorized_user.credential.id, expected_version, &db) {
+        Ok(u) => response_user(&u),
+        Err(UserUpdateError::InvalidParameter(e)) => HttpResponse::BadRequest().json(
+            hashmap! { "error" => ApiError::new_with_detail(ApiErrorCode::InvalidRequest, "Invalid parameter.", e) }
+        ),
+        Err(UserUpdateError::PreconditionFailed) => precondition_failed_response(),
+        Err(UserUpdateError::NotFound) => ApiError::new(ApiErrorCode::NotFound, "User does not exist.").error_response(),
+        Err(UserUpdateError::Failed) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+pub async fn update_my_id_name(new_id_name: Option<web::Json<InputUserIdName>>, request: HttpRequest, authorized_user: web::ReqData<AuthorizedUser>, db: web::Data<DBConPool>) -> impl Responder {
+    let user = match &authorized_user.user {
+        Some(u) => u,
+        None => return ApiError::new(ApiErrorCode::NotFound, "Create user first.").error_response()
+    };
+    
+    if is_precondition_failed(&request, &user.entity_tag()) {
+        return precondition_failed_response();
+    }
+    
+    let new_id_name = match new_id_name {
+        None => return parse_error_response(),
+        Some(n) => n
+    };
+    
+    let expected_version = has_if_match_tags(&request).then(|| user.version);
+    
+    match User::change_id_name(new_id_name.0, user, expected_version, &db) {
+        Ok(u) => response_user(&u),
         Err(e) => e.error_response()
     }
 }
 
-pub async fn show_me(authorized_user: web::ReqData<AuthorizedUser>) -> impl Responder {
+pub async fn delete_me(input: Option<web::Json<InputPassword>>, authorized_user: web::ReqData<AuthorizedUser>, hashing_pool: web::Data<PasswordHashingPool>, db: web::Data<DBConPool>) -> impl Responder {
+    let input = match input {
+        None => return parse_error_response(),
+        Some(i) => i
+    };
+    
+    let credential = authorized_user.credential.clone();
+    
+    match hashing_pool.run(move || credential.verify_password(&input.password)).await {
+        Ok(true) => {}
+        Ok(false) => return ApiError::new(ApiErrorCode::AuthFailed, "Invalid credentials.").error_response(),
+        Err(_) => return HttpResponse::InternalServerError().finish()
+    }
+    
+    match authorized_user.credential.delete_account(&db) {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+pub async fn deactivate_me(authorized_user: web::ReqData<AuthorizedUser>, db: web::Data<DBConPool>) -> impl Responder {
+    let user = match &authorized_user.user {
+        Some(u) => u,
+        None => return ApiError::new(ApiErrorCode::NotFound, "Create user first.").error_response()
+    };
+    
+    if let Err(e) = UserToken::revoke_others(&authorized_user.credential.id, &authorized_user.token, &db) {
+        return e.error_response();
+    }
+    
+    if PersonalAccessToken::revoke_all(&authorized_user.credential.id, &crate::get_db_connection(&db)).is_err() {
+        return HttpResponse::InternalServerError().finish();
+    }
+    
+    match user.deactivate(&db) {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish()
+    }
+}
+
+pub async fn show_me(request: HttpRequest, authorized_user: web::ReqData<AuthorizedUser>) -> impl Responder {
     authorized_user.user.as_ref()
                    .ok_or(ApiError::new(ApiErrorCode::NotFound, "Create user first.").error_response())
-                   .map(|u| {
-                       HttpResponse::Ok().json(
-                           hashmap! { "user" => u.filter_for_response() }
-                       )
-                   })
+                   .map(|u| response_conditional_user(&request, u))
+}
+
+fn response_fetch_user(request: &HttpRequest, user: diesel::QueryResult<User>) -> HttpResponse {
+    match user {
+        Ok(user) if user.is_deactivated() => deactivated_user_response(),
+        Ok(user) => response_conditional_user(request, &user),
+        Err(diesel::NotFound) => HttpResponse::NotFound().json(
+            hashmap! { "error" => ApiError::new(ApiErrorCode::NotFound, "User does not exist.") }
+        ),
+        _ => HttpResponse::InternalServerError().finish()
+    }
+}
+
+fn response_conditional_user(request: &HttpRequest, user: &User) -> HttpResponse {
+    let entity_tag = user.entity_tag();
+    
+    if is_not_modified(request, &entity_tag) {
+        return not_modified_response(entity_tag);
+    }
+    
+    response_user(user)
+}
+
+fn response_user(user: &User) -> HttpResponse {
+    HttpResponse::Ok().set(ETag(user.entity_tag())).json(
+        hashmap! { "user" => user.filter_for_response() }
+    )
 }
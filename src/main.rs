@@ -5,7 +5,7 @@ extern crate serde_derive;
 extern crate strum;
 
 use actix_cors::Cors;
-use actix_web::{App, HttpResponse, HttpServer, web};
+use actix_web::{App, http, HttpResponse, HttpServer, web};
 use actix_web::middleware::Logger;
 use diesel::r2d2;
 use diesel::r2d2::{ConnectionManager, PooledConnection};
@@ -30,24 +30,54 @@ async fn main() -> std::io::Result<()> {
     dotenv().ok();
     env_logger::init();
     
-    HttpServer::new(|| {
-        let database_url = std::env::var("DATABASE_URL").expect("invalid DATABASE_URL");
+    let database_url = std::env::var("DATABASE_URL").expect("invalid DATABASE_URL");
+    let pool: DBConPool = r2d2::Pool::builder()
+        .connection_timeout(std::time::Duration::from_secs(10))
+        .build(r2d2::ConnectionManager::<diesel::MysqlConnection>::new(database_url))
+        .expect("Failed to establish DB connection");
+    
+    // A bad setting should stop the server here rather than fail requests later.
+    models::user_token::init_token_hash_key();
+    models::user_credential::init_argon2_params();
+    services::data_export::init_export_dir();
+    services::trusted_proxy::init_from_env();
+    models::security_event::init_retention_days();
+    
+    let mailer = services::mailer::from_env();
+    let registration_mode = models::registration_mode::RegistrationMode::from_env();
+    // Shared by all workers, so that the cap on concurrent hashes holds for the whole server.
+    let hashing_pool = web::Data::new(services::password_hashing::PasswordHashingPool::from_env());
+    
+    services::token_migration::hash_legacy_tokens(&pool);
+    services::account_purge::spawn(pool.clone());
+    
+    HttpServer::new(move || {
         App::new()
             .wrap(Logger::default())
             .wrap(
                 Cors::default()
                     .allowed_origin(std::env::var("FRONTEND_URL").expect("invalid FRONTEND_URL").as_str())
+                    .allowed_methods(vec!["GET", "POST", "PUT", "PATCH", "DELETE"])
+                    .allowed_header(http::header::AUTHORIZATION)
+                    .allowed_header(http::header::CONTENT_TYPE)
+                    .allowed_header(http::header::IF_MATCH)
+                    .allowed_header(http::header::IF_NONE_MATCH)
+                    .allowed_header(services::session_cookie::CSRF_HEADER_NAME)
+                    .allowed_header(services::session_cookie::SESSION_MODE_HEADER_NAME)
+                    .supports_credentials()
             )
-            .data(
-                r2d2::Pool::builder()
-                    .connection_timeout(std::time::Duration::from_secs(10))
-                    .build(r2d2::ConnectionManager::<diesel::MysqlConnection>::new(database_url))
-                    .expect("Failed to establish DB connection")
-            )
+            .data(pool.clone())
+            .data(mailer.clone())
+            .data(registration_mode)
+            .app_data(hashing_pool.clone())
             .service(web::scope("/api")
                 .configure(routes::users)
                 .configure(routes::posts)
                 .configure(routes::auth)
+                .configure(routes::admin)
+                .configure(routes::oauth)
+                .configure(routes::search)
+                .configure(routes::exports)
             )
             .default_service(
                 web::route().to(|| HttpResponse::NotFound().json(
@@ -1,5 +1,7 @@
 use chrono::TimeZone;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+
+const RESEND_INTERVAL_MINUTES: i64 = 1;
 
 macro_rules! response_item_insertion_result {
     ($modified_rows_count:expr, $response_data:expr) => {
@@ -15,11 +17,42 @@ macro_rules! response_item_insertion_result {
     }
 }
 
+/// Keeps the tokens that are neither used up nor expired. Expects the table's `dsl` to be in scope.
+macro_rules! filter_for_active_tokens {
+    ($query:expr) => {
+        $query
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::expired_at.gt(crate::models::get_now_naive_date_time()))
+    }
+}
+
+pub mod auth_throttle;
+pub mod email_change_token;
+pub mod email_verification_token;
 pub mod error;
+pub mod invite_code;
+pub mod magic_link_token;
 pub mod user;
 pub mod user_credential;
+pub mod user_data_export;
+pub mod user_handle_history;
+pub mod user_image;
+pub mod user_recovery_code;
+pub mod user_refresh_token;
+pub mod user_session;
 pub mod user_token;
 pub mod post;
+pub mod post_import;
+pub mod post_search;
+pub mod oauth_app;
+pub mod oauth_authorization_code;
+pub mod password_reset_token;
+pub mod personal_access_token;
+pub mod registration_mode;
+pub mod security_event;
+pub mod token_scope;
+pub mod totp;
+pub mod two_factor_challenge;
 
 pub(in crate::models) fn serialize_naive_dt<S>(date: &chrono::NaiveDateTime, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -38,10 +71,32 @@ pub(in crate::models) fn serialize_option_naive_dt<S>(date: &Option<chrono::Naiv
     }
 }
 
+pub(in crate::models) fn deserialize_patch_field<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+    where
+        T: serde::Deserialize<'de>,
+        D: serde::Deserializer<'de>,
+{
+    // Only called when the field is present, so an explicit `null` becomes `Some(None)`.
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
 pub(in crate::models) fn get_now_date_time() -> chrono::DateTime<chrono::Local> {
     chrono::Local::now()
 }
 
+/// Whether a mailed token was issued too recently to send another one.
+pub(in crate::models) fn is_resend_too_soon(last_issued_at: Option<chrono::NaiveDateTime>) -> bool {
+    last_issued_at.map_or(false, |t| t > (get_now_date_time() - chrono::Duration::minutes(RESEND_INTERVAL_MINUTES)).naive_local())
+}
+
 pub(in crate::models) fn get_now_naive_date_time() -> chrono::NaiveDateTime {
     get_now_date_time().naive_local()
 }
+
+pub(in crate::models) fn escape_like_pattern(text: &str) -> String {
+    text.chars().fold(String::with_capacity(text.len()), |mut escaped, c| {
+        if matches!(c, '\\' | '%' | '_') { escaped.push('\\'); }
+        escaped.push(c);
+        escaped
+    })
+}
@@ -0,0 +1,145 @@
+use diesel::prelude::*;
+
+use crate::DBConPool;
+use crate::models::{get_now_date_time, get_now_naive_date_time};
+use crate::models::user_token::hash_token;
+use crate::schema::auth_throttles;
+
+// Failures older than this are forgotten, so that a lockout does not grow across unrelated days.
+const FAILURE_WINDOW_HOURS: i64 = 24;
+
+pub struct ThrottlePolicy {
+    free_attempts: i32,
+    base_lock_seconds: i64,
+    max_lock_seconds: i64,
+}
+
+const ACCOUNT_LOGIN_POLICY: ThrottlePolicy = ThrottlePolicy { free_attempts: 5, base_lock_seconds: 30, max_lock_seconds: 60 * 60 };
+// An address may be shared by many users, so it gets more attempts before it is locked.
+const IP_LOGIN_POLICY: ThrottlePolicy = ThrottlePolicy { free_attempts: 20, base_lock_seconds: 60, max_lock_seconds: 60 * 60 };
+const IP_REGISTRATION_POLICY: ThrottlePolicy = ThrottlePolicy { free_attempts: 5, base_lock_seconds: 10 * 60, max_lock_seconds: 24 * 60 * 60 };
+
+/// Identifies what is throttled. The key is stored hashed, so the table holds no emails or addresses.
+pub struct ThrottleKey {
+    key: String,
+    policy: &'static ThrottlePolicy,
+}
+
+#[derive(Insertable)]
+#[table_name = "auth_throttles"]
+pub struct InsertableAuthThrottle {
+    throttle_key: String,
+}
+
+#[derive(Queryable)]
+pub struct AuthThrottle {
+    pub throttle_key: String,
+    pub failed_count: i32,
+    pub locked_until: Option<chrono::NaiveDateTime>,
+    pub last_failed_at: Option<chrono::NaiveDateTime>,
+}
+
+impl ThrottleKey {
+    pub fn login_account(email: &str) -> Self {
+        Self::new(&format!("login:account:{}", email.trim().to_lowercase()), &ACCOUNT_LOGIN_POLICY)
+    }
+    
+    pub fn login_ip(ip_address: &str) -> Self {
+        Self::new(&format!("login:ip:{}", ip_address), &IP_LOGIN_POLICY)
+    }
+    
+    pub fn registration_ip(ip_address: &str) -> Self {
+        Self::new(&format!("registration:ip:{}", ip_address), &IP_REGISTRATION_POLICY)
+    }
+    
+    fn new(key: &str, policy: &'static ThrottlePolicy) -> Self {
+        Self { key: hash_token(key), policy }
+    }
+}
+
+impl AuthThrottle {
+    /// Returns the seconds until the longest running lockout among `keys` ends.
+    pub fn retry_after(keys: &[ThrottleKey], db: &DBConPool) -> QueryResult<Option<i64>> {
+        use crate::schema::auth_throttles::dsl;
+        
+        let now = get_now_naive_date_time();
+        
+        let locked_until = dsl::auth_throttles
+            .select(dsl::locked_until)
+            .filter(dsl::throttle_key.eq_any(keys.iter().map(|k| &k.key)))
+            .filter(dsl::locked_until.gt(now))
+            .order(dsl::locked_until.desc())
+            .first::<Option<chrono::NaiveDateTime>>(&crate::get_db_connection(db))
+            .optional()?;
+        
+        Ok(locked_until.flatten().map(|t| (t - now).num_seconds().max(1)))
+    }
+    
+    /// Counts a failure and locks the key with an exponential backoff once the free attempts are used up.
+    /// Returns whether this failure started the first lockout of the window, which is worth telling the owner.
+    pub fn record_failure(key: &ThrottleKey, db: &DBConPool) -> QueryResult<bool> {
+        use crate::schema::auth_throttles::dsl;
+        
+        let conn = crate::get_db_connection(db);
+        let now = get_now_date_time();
+        
+        conn.transaction(|| {
+            diesel::insert_or_ignore_into(dsl::auth_throttles)
+                .values(&InsertableAuthThrottle { throttle_key: key.key.clone() })
+                .execute(&conn)?;
+            
+            let throttle = dsl::auth_throttles
+                .select((dsl::throttle_key, dsl::failed_count, dsl::locked_until, dsl::last_failed_at))
+                .filter(dsl::throttle_key.eq(&key.key))
+                .for_update()
+                .first::<Self>(&conn)?;
+            
+            let window_start = (now - chrono::Duration::hours(FAILURE_WINDOW_HOURS)).naive_local();
+            let failed_count = match throttle.last_failed_at {
+                Some(t) if t > window_start => throttle.failed_count + 1,
+                _ => 1,
+            };
+            
+            let excess_attempts = failed_count - key.policy.free_attempts;
+            let locked_until = match excess_attempts {
+                e if e > 0 => {
+                    let lock_seconds = key.policy.base_lock_seconds
+                        .saturating_mul(1 << (e - 1).min(20))
+                        .min(key.policy.max_lock_seconds);
+                    Some((now + chrono::Duration::seconds(lock_seconds)).naive_local())
+                }
+                _ => None,
+            };
+            
+            diesel::update(dsl::auth_throttles.filter(dsl::throttle_key.eq(&key.key)))
+                .set((
+                    dsl::failed_count.eq(failed_count),
+                    dsl::locked_until.eq(locked_until),
+                    dsl::last_failed_at.eq(now.naive_local()),
+                ))
+                .execute(&conn)?;
+            
+            Ok(excess_attempts == 1)
+        })
+    }
+    
+    pub fn reset(key: &ThrottleKey, db: &DBConPool) -> QueryResult<usize> {
+        use crate::schema::auth_throttles::dsl;
+        
+        diesel::delete(dsl::auth_throttles.filter(dsl::throttle_key.eq(&key.key)))
+            .execute(&crate::get_db_connection(db))
+    }
+    
+    pub fn purge_stale(db: &DBConPool) -> QueryResult<usize> {
+        use crate::schema::auth_throttles::dsl;
+        
+        let now = get_now_date_time();
+        let window_start = (now - chrono::Duration::hours(FAILURE_WINDOW_HOURS)).naive_local();
+        
+        diesel::delete(dsl::auth_throttles
+            .filter(dsl::last_failed_at.is_null().or(dsl::last_failed_at.lt(window_start)))
+            .filter(dsl::locked_until.is_null().or(dsl::locked_until.lt(now.naive_local())))
+        )
+            .execute(&crate::get_db_connection(db))
+    }
+}
\ No newline at end of file
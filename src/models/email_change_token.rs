@@ -0,0 +1,167 @@
+use diesel::prelude::*;
+use diesel::result::{DatabaseErrorKind, Error};
+use validator::Validate;
+
+use crate::{DBConnection, DBConPool};
+use crate::models::{get_now_date_time, get_now_naive_date_time, is_resend_too_soon};
+use crate::models::error::{ApiError, ApiErrorCode};
+use crate::models::user_credential::{InputUserCredential, UserCredential};
+use crate::models::user_token::{generate_token, hash_token};
+use crate::schema::{email_change_tokens, user_credentials};
+
+const EMAIL_CHANGE_TOKEN_PREFIX: &str = "rsns_ec_";
+const EMAIL_CHANGE_VALIDITY_HOURS: i64 = 24;
+
+#[derive(Deserialize)]
+pub struct InputEmailChange {
+    #[serde(rename = "password")]
+    pub current_password: String,
+    pub email: String,
+}
+
+#[derive(Deserialize)]
+pub struct InputEmailChangeConfirmation {
+    pub token: String,
+}
+
+#[derive(Insertable)]
+#[table_name = "email_change_tokens"]
+pub struct InsertableEmailChangeToken {
+    token: String,
+    user_id: String,
+    new_email: String,
+    expired_at: chrono::NaiveDateTime,
+}
+
+#[derive(Queryable)]
+pub struct EmailChangeToken {
+    pub token: String,
+    pub user_id: String,
+    pub new_email: String,
+}
+
+pub struct ChangedEmail {
+    pub user_id: String,
+    pub old_email: String,
+    pub new_email: String,
+}
+
+impl EmailChangeToken {
+    /// Returns the raw token, which is mailed to the new address to prove that the user owns it.
+    pub fn issue(credential: &UserCredential, input: InputEmailChange, db: &DBConPool) -> Result<String, ApiError> {
+        use crate::schema::email_change_tokens::dsl;
+        
+        if !credential.verify_password(&input.current_password) {
+            return Err(ApiError::new(ApiErrorCode::AuthFailed, "Invalid credentials."));
+        }
+        
+        let new_credential = InputUserCredential { raw_password: input.current_password, email: input.email };
+        
+        if let Err(_) = new_credential.validate() {
+            return Err(ApiError::new(ApiErrorCode::InvalidRequest, "Invalid parameter."));
+        }
+        if new_credential.email == credential.email {
+            return Err(ApiError::new(ApiErrorCode::InvalidRequest, "Email is not changed."));
+        }
+        
+        let conn = crate::get_db_connection(db);
+        let now = get_now_date_time();
+        
+        let is_taken = user_credentials::table
+            .select(user_credentials::id)
+            .filter(user_credentials::email.eq(&new_credential.email))
+            .first::<String>(&conn)
+            .optional()
+            .map_err(|_| ApiError::new(ApiErrorCode::ServerError, "Failed to request email change."))?
+            .is_some();
+        
+        if is_taken {
+            return Err(ApiError::new(ApiErrorCode::InvalidRequest, "Email is already taken."));
+        }
+        
+        let last_issued_at = filter_for_active_tokens!(dsl::email_change_tokens)
+            .select(dsl::created_at)
+            .filter(dsl::user_id.eq(&credential.id))
+            .order(dsl::created_at.desc())
+            .first::<chrono::NaiveDateTime>(&conn)
+            .optional()
+            .map_err(|_| ApiError::new(ApiErrorCode::ServerError, "Failed to request email change."))?;
+        
+        if is_resend_too_soon(last_issued_at) {
+            return Err(ApiError::new(ApiErrorCode::RateLimited, "Email change was requested recently."));
+        }
+        
+        let raw_token = generate_token(EMAIL_CHANGE_TOKEN_PREFIX);
+        
+        conn.transaction(|| {
+            Self::invalidate_by_user(&credential.id, &conn)?;
+            diesel::insert_into(dsl::email_change_tokens)
+                .values(&InsertableEmailChangeToken {
+                    token: hash_token(&raw_token),
+                    user_id: credential.id.clone(),
+                    new_email: new_credential.email.clone(),
+                    expired_at: (now + chrono::Duration::hours(EMAIL_CHANGE_VALIDITY_HOURS)).naive_local(),
+                })
+                .execute(&conn)
+        })
+            .map(|_| raw_token)
+            .map_err(|_| ApiError::new(ApiErrorCode::ServerError, "Failed to request email change."))
+    }
+    
+    pub fn confirm(token: &String, db: &DBConPool) -> Result<ChangedEmail, ApiError> {
+        use crate::schema::email_change_tokens::dsl;
+        
+        let conn = crate::get_db_connection(db);
+        
+        let change_token = filter_for_active_tokens!(dsl::email_change_tokens)
+            .select((dsl::token, dsl::user_id, dsl::new_email))
+            .filter(dsl::token.eq(hash_token(token)))
+            .first::<Self>(&conn)
+            .map_err(|_| ApiError::new(ApiErrorCode::InvalidToken, "Email change token is invalid or expired."))?;
+        
+        let credential = UserCredential::fetch_by_id(&change_token.user_id, db)
+            .map_err(|_| ApiError::new(ApiErrorCode::InvalidToken, "Email change token is invalid or expired."))?;
+        
+        let result = conn.transaction::<_, Error, _>(|| {
+            let marked_count = diesel::update(dsl::email_change_tokens
+                .filter(dsl::token.eq(&change_token.token))
+                .filter(dsl::deleted_at.is_null())
+            )
+                .set(dsl::deleted_at.eq(get_now_naive_date_time()))
+                .execute(&conn)?;
+            
+            if marked_count == 0 {
+                return Ok(false);
+            }
+            
+            Self::invalidate_by_user(&change_token.user_id, &conn)?;
+            
+            // Following the link proves the new address, so it does not need another verification.
+            diesel::update(user_credentials::table
+                .filter(user_credentials::id.eq(&credential.id))
+                .filter(user_credentials::email.eq(&credential.email))
+            )
+                .set((
+                    user_credentials::email.eq(&change_token.new_email),
+                    user_credentials::email_verified_at.eq(get_now_naive_date_time()),
+                ))
+                .execute(&conn)
+                .map(|count| count > 0)
+        });
+        
+        match result {
+            Ok(true) => Ok(ChangedEmail { user_id: credential.id, old_email: credential.email, new_email: change_token.new_email }),
+            Ok(false) => Err(ApiError::new(ApiErrorCode::InvalidToken, "Email change token is invalid or expired.")),
+            Err(Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => Err(ApiError::new(ApiErrorCode::InvalidRequest, "Email is already taken.")),
+            Err(_) => Err(ApiError::new(ApiErrorCode::ServerError, "Failed to change email.")),
+        }
+    }
+    
+    fn invalidate_by_user(user_id: &String, conn: &DBConnection) -> QueryResult<usize> {
+        use crate::schema::email_change_tokens::dsl;
+        
+        diesel::update(dsl::email_change_tokens.filter(dsl::user_id.eq(user_id)).filter(dsl::deleted_at.is_null()))
+            .set(dsl::deleted_at.eq(get_now_naive_date_time()))
+            .execute(conn)
+    }
+}
\ No newline at end of file
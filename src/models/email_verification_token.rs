@@ -0,0 +1,105 @@
+use diesel::prelude::*;
+
+use crate::DBConPool;
+use crate::models::{get_now_date_time, get_now_naive_date_time, is_resend_too_soon};
+use crate::models::error::{ApiError, ApiErrorCode};
+use crate::models::user_token::{generate_token, hash_token};
+use crate::schema::{email_verification_tokens, user_credentials};
+
+const EMAIL_VERIFICATION_TOKEN_PREFIX: &str = "rsns_ev_";
+const EMAIL_VERIFICATION_VALIDITY_HOURS: i64 = 24;
+
+#[derive(Deserialize)]
+pub struct InputEmailVerification {
+    pub token: String,
+}
+
+#[derive(Insertable)]
+#[table_name = "email_verification_tokens"]
+pub struct InsertableEmailVerificationToken {
+    token: String,
+    user_id: String,
+    email: String,
+    expired_at: chrono::NaiveDateTime,
+}
+
+#[derive(Queryable)]
+pub struct EmailVerificationToken {
+    pub token: String,
+    pub user_id: String,
+    pub email: String,
+}
+
+impl EmailVerificationToken {
+    /// Issues a token for `email`, replacing the tokens sent before. Returns the raw token for the mail.
+    pub fn issue(user_id: &String, email: &String, db: &DBConPool) -> Result<String, ApiError> {
+        use crate::schema::email_verification_tokens::dsl;
+        
+        let conn = crate::get_db_connection(db);
+        let now = get_now_date_time();
+        
+        let last_issued_at = filter_for_active_tokens!(dsl::email_verification_tokens)
+            .select(dsl::created_at)
+            .filter(dsl::user_id.eq(user_id))
+            .order(dsl::created_at.desc())
+            .first::<chrono::NaiveDateTime>(&conn)
+            .optional()
+            .map_err(|_| ApiError::new(ApiErrorCode::ServerError, "Failed to issue verification token."))?;
+        
+        if is_resend_too_soon(last_issued_at) {
+            return Err(ApiError::new(ApiErrorCode::RateLimited, "Verification mail was sent recently."));
+        }
+        
+        let raw_token = generate_token(EMAIL_VERIFICATION_TOKEN_PREFIX);
+        
+        conn.transaction::<_, diesel::result::Error, _>(|| {
+            diesel::update(dsl::email_verification_tokens.filter(dsl::user_id.eq(user_id)).filter(dsl::deleted_at.is_null()))
+                .set(dsl::deleted_at.eq(now.naive_local()))
+                .execute(&conn)?;
+            diesel::insert_into(dsl::email_verification_tokens)
+                .values(&InsertableEmailVerificationToken {
+                    token: hash_token(&raw_token),
+                    user_id: user_id.clone(),
+                    email: email.clone(),
+                    expired_at: (now + chrono::Duration::hours(EMAIL_VERIFICATION_VALIDITY_HOURS)).naive_local(),
+                })
+                .execute(&conn)
+        })
+            .map(|_| raw_token)
+            .map_err(|_| ApiError::new(ApiErrorCode::ServerError, "Failed to issue verification token."))
+    }
+    
+    pub fn confirm(token: &String, db: &DBConPool) -> Result<(), ApiError> {
+        use crate::schema::email_verification_tokens::dsl;
+        
+        let conn = crate::get_db_connection(db);
+        
+        let verification_token = filter_for_active_tokens!(dsl::email_verification_tokens)
+            .select((dsl::token, dsl::user_id, dsl::email))
+            .filter(dsl::token.eq(hash_token(token)))
+            .first::<Self>(&conn)
+            .map_err(|_| ApiError::new(ApiErrorCode::InvalidToken, "Verification token is invalid or expired."))?;
+        
+        let now = get_now_naive_date_time();
+        
+        // The address must still be the account's, so a token sent before an email change cannot verify the new one.
+        let result = conn.transaction::<_, diesel::result::Error, _>(|| {
+            diesel::update(dsl::email_verification_tokens.filter(dsl::token.eq(&verification_token.token)))
+                .set(dsl::deleted_at.eq(now))
+                .execute(&conn)?;
+            diesel::update(user_credentials::table
+                .filter(user_credentials::id.eq(&verification_token.user_id))
+                .filter(user_credentials::email.eq(&verification_token.email))
+                .filter(user_credentials::deleted_at.is_null())
+            )
+                .set(user_credentials::email_verified_at.eq(now))
+                .execute(&conn)
+        });
+        
+        match result {
+            Ok(count) if count > 0 => Ok(()),
+            Ok(_) => Err(ApiError::new(ApiErrorCode::InvalidToken, "Verification token is invalid or expired.")),
+            Err(_) => Err(ApiError::new(ApiErrorCode::ServerError, "Failed to verify email.")),
+        }
+    }
+}
\ No newline at end of file
@@ -10,6 +10,16 @@ pub enum ApiErrorCode {
     NotAllowed,
     AuthFailed,
     InvalidToken,
+    InvalidCsrfToken,
+    InsufficientScope,
+    AccountDeleted,
+    AccountDeactivated,
+    EmailNotVerified,
+    AccountPendingApproval,
+    RegistrationClosed,
+    PermissionDenied,
+    PreconditionFailed,
+    RateLimited,
     ServerError,
 }
 
@@ -42,6 +52,16 @@ impl ApiError {
             ApiErrorCode::NotAllowed => HttpResponse::MethodNotAllowed(),
             ApiErrorCode::AuthFailed => HttpResponse::Unauthorized().header(header::WWW_AUTHENTICATE, "Bearer").take(),
             ApiErrorCode::InvalidToken => HttpResponse::Unauthorized().header(header::WWW_AUTHENTICATE, "Bearer error=\"invalid_token\"").take(),
+            ApiErrorCode::InvalidCsrfToken => HttpResponse::Forbidden(),
+            ApiErrorCode::InsufficientScope => HttpResponse::Forbidden().header(header::WWW_AUTHENTICATE, "Bearer error=\"insufficient_scope\"").take(),
+            ApiErrorCode::AccountDeleted => HttpResponse::Forbidden(),
+            ApiErrorCode::AccountDeactivated => HttpResponse::Forbidden(),
+            ApiErrorCode::EmailNotVerified => HttpResponse::Forbidden(),
+            ApiErrorCode::AccountPendingApproval => HttpResponse::Forbidden(),
+            ApiErrorCode::RegistrationClosed => HttpResponse::Forbidden(),
+            ApiErrorCode::PermissionDenied => HttpResponse::Forbidden(),
+            ApiErrorCode::PreconditionFailed => HttpResponse::PreconditionFailed(),
+            ApiErrorCode::RateLimited => HttpResponse::TooManyRequests(),
             ApiErrorCode::ServerError => HttpResponse::InternalServerError(),
         }.json(
             hashmap! { "error" => self }
@@ -59,3 +79,22 @@ pub struct ApiErrorWithDetail<T>
     pub detail: T,
 }
 
+#[derive(Serialize)]
+pub struct OAuthError {
+    pub error: &'static str,
+    pub error_description: &'static str,
+}
+
+impl OAuthError {
+    pub fn new(error: &'static str, error_description: &'static str) -> Self {
+        Self { error, error_description }
+    }
+    
+    pub fn error_response(&self) -> HttpResponse {
+        match self.error {
+            "invalid_client" => HttpResponse::Unauthorized(),
+            "server_error" => HttpResponse::InternalServerError(),
+            _ => HttpResponse::BadRequest(),
+        }.json(self)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,178 @@
+use diesel::prelude::*;
+use log::error;
+use validator::{Validate, ValidationError, ValidationErrors};
+
+use crate::{DBConnection, DBConPool};
+use crate::models::{get_now_date_time, get_now_naive_date_time};
+use crate::models::user_credential::UserCredential;
+use crate::models::user_token::{generate_token, hash_token};
+use crate::schema::invite_codes;
+
+const INVITE_CODE_PREFIX: &str = "rsns_iv_";
+const DEFAULT_INVITE_MAX_USES: i32 = 1;
+// Users other than administrators get a few single-use codes a month, so that invitations stay personal.
+const MEMBER_INVITE_MAX_USES: i32 = 1;
+const MEMBER_INVITE_QUOTA: i64 = 5;
+const MEMBER_INVITE_QUOTA_DAYS: i64 = 30;
+
+#[derive(Deserialize, Validate)]
+pub struct InputInviteCode {
+    #[validate(range(min = 1, max = 100))]
+    pub max_uses: Option<i32>,
+    #[validate(range(min = 1, max = 30))]
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Insertable)]
+#[table_name = "invite_codes"]
+pub struct InsertableInviteCode {
+    id: String,
+    inviter_id: String,
+    code: String,
+    max_uses: i32,
+    expired_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Serialize, Queryable)]
+pub struct InviteCode {
+    pub id: String,
+    #[serde(skip)]
+    pub inviter_id: String,
+    #[serde(skip)]
+    pub code: String,
+    pub max_uses: i32,
+    pub used_count: i32,
+    #[serde(serialize_with = "crate::models::serialize_option_naive_dt")]
+    pub expired_at: Option<chrono::NaiveDateTime>,
+    #[serde(serialize_with = "crate::models::serialize_naive_dt")]
+    pub created_at: chrono::NaiveDateTime,
+    #[serde(skip)]
+    pub updated_at: chrono::NaiveDateTime,
+    #[serde(skip)]
+    pub deleted_at: Option<chrono::NaiveDateTime>,
+}
+
+pub enum InviteCodeIssueError {
+    InvalidParameter(ValidationErrors),
+    QuotaExceeded,
+    Failed,
+}
+
+#[derive(Serialize)]
+pub struct IssuedInviteCode {
+    #[serde(flatten)]
+    invite_code: InviteCode,
+    code: String,
+}
+
+macro_rules! filter_for_active_codes {
+    ($query:expr) => {
+        $query
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::expired_at.is_null().or(dsl::expired_at.gt(get_now_naive_date_time())))
+    }
+}
+
+impl InviteCode {
+    pub fn issue(inviter: &UserCredential, input: InputInviteCode, db: &DBConPool) -> Result<IssuedInviteCode, InviteCodeIssueError> {
+        use crate::schema::invite_codes::dsl;
+        
+        let mut errors = input.validate().err().unwrap_or_else(ValidationErrors::new);
+        
+        if !inviter.is_admin && input.max_uses.map_or(false, |m| m > MEMBER_INVITE_MAX_USES) {
+            errors.add("max_uses", ValidationError::new("range"));
+        }
+        if !errors.is_empty() {
+            return Err(InviteCodeIssueError::InvalidParameter(errors));
+        }
+        
+        if !inviter.is_admin {
+            // Revoked and used up codes count as well, so that they cannot be traded in for new ones.
+            let recent_count = dsl::invite_codes
+                .filter(dsl::inviter_id.eq(&inviter.id))
+                .filter(dsl::created_at.gt((get_now_date_time() - chrono::Duration::days(MEMBER_INVITE_QUOTA_DAYS)).naive_local()))
+                .count()
+                .get_result::<i64>(&crate::get_db_connection(db))
+                .map_err(|e| {
+                    error!("query was failed: {:?}", e);
+                    InviteCodeIssueError::Failed
+                })?;
+            
+            if recent_count >= MEMBER_INVITE_QUOTA {
+                return Err(InviteCodeIssueError::QuotaExceeded);
+            }
+        }
+        
+        let inviter_id = &inviter.id;
+        let raw_code = generate_token(INVITE_CODE_PREFIX);
+        
+        let insertable_code = InsertableInviteCode {
+            id: uuid::Uuid::new_v4().to_string(),
+            inviter_id: inviter_id.clone(),
+            code: hash_token(&raw_code),
+            max_uses: input.max_uses.unwrap_or(DEFAULT_INVITE_MAX_USES),
+            expired_at: input.expires_in_days.map(|d| (get_now_date_time() + chrono::Duration::days(d)).naive_local()),
+        };
+        
+        let result = diesel::insert_into(dsl::invite_codes)
+            .values(&insertable_code)
+            .execute(&crate::get_db_connection(db))
+            .and_then(|_| Self::fetch_by_id(&insertable_code.id, inviter_id, db));
+        
+        match result {
+            Ok(c) => Ok(IssuedInviteCode { invite_code: c, code: raw_code }),
+            Err(e) => {
+                error!("query was failed: {:?}", e);
+                Err(InviteCodeIssueError::Failed)
+            }
+        }
+    }
+    
+    pub fn fetch_by_id(code_id: &String, inviter_id: &String, db: &DBConPool) -> QueryResult<Self> {
+        use crate::schema::invite_codes::dsl;
+        
+        filter_for_active_codes!(dsl::invite_codes)
+            .filter(dsl::id.eq(code_id))
+            .filter(dsl::inviter_id.eq(inviter_id))
+            .first::<Self>(&crate::get_db_connection(db))
+    }
+    
+    pub fn fetch_list_by_inviter(inviter_id: &String, db: &DBConPool) -> QueryResult<Vec<Self>> {
+        use crate::schema::invite_codes::dsl;
+        
+        filter_for_active_codes!(dsl::invite_codes)
+            .filter(dsl::inviter_id.eq(inviter_id))
+            .order(dsl::created_at.desc())
+            .load::<Self>(&crate::get_db_connection(db))
+    }
+    
+    /// Counts one use of the code, or returns `None` when it is unknown, expired or used up.
+    /// Meant to run in the transaction that creates the account, so that a failed registration gives the use back.
+    pub fn redeem(raw_code: &String, conn: &DBConnection) -> QueryResult<Option<Self>> {
+        use crate::schema::invite_codes::dsl;
+        
+        let invite_code = filter_for_active_codes!(dsl::invite_codes)
+            .filter(dsl::code.eq(hash_token(raw_code)))
+            .for_update()
+            .first::<Self>(conn)
+            .optional()?;
+        
+        match invite_code {
+            Some(c) if c.used_count < c.max_uses => {
+                diesel::update(dsl::invite_codes.filter(dsl::id.eq(&c.id)))
+                    .set(dsl::used_count.eq(dsl::used_count + 1))
+                    .execute(conn)?;
+                Ok(Some(c))
+            }
+            _ => Ok(None),
+        }
+    }
+    
+    pub fn revoke(&self, db: &DBConPool) -> QueryResult<usize> {
+        use crate::schema::invite_codes::dsl;
+        
+        diesel::update(dsl::invite_codes.filter(dsl::id.eq(&self.id)))
+            .set(dsl::deleted_at.eq(get_now_naive_date_time()))
+            .execute(&crate::get_db_connection(db))
+    }
+}
\ No newline at end of file
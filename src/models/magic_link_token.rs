@@ -0,0 +1,128 @@
+use diesel::prelude::*;
+
+use crate::{DBConnection, DBConPool};
+use crate::models::{get_now_date_time, get_now_naive_date_time, is_resend_too_soon};
+use crate::models::error::{ApiError, ApiErrorCode};
+use crate::models::user_credential::UserCredential;
+use crate::models::user_token::{generate_token, hash_token};
+use crate::schema::magic_link_tokens;
+
+const MAGIC_LINK_TOKEN_PREFIX: &str = "rsns_ml_";
+pub const MAGIC_LINK_VALIDITY_MINUTES: i64 = 15;
+
+#[derive(Deserialize)]
+pub struct InputMagicLinkRequest {
+    pub email: String,
+    pub device_name: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct InputMagicLinkLogin {
+    pub token: String,
+}
+
+#[derive(Deserialize)]
+pub struct InputMagicLinkSetting {
+    pub enabled: bool,
+}
+
+#[derive(Insertable)]
+#[table_name = "magic_link_tokens"]
+pub struct InsertableMagicLinkToken {
+    token: String,
+    user_id: String,
+    device_name: Option<String>,
+    expired_at: chrono::NaiveDateTime,
+}
+
+#[derive(Queryable)]
+pub struct MagicLinkToken {
+    pub token: String,
+    pub user_id: String,
+    pub device_name: Option<String>,
+}
+
+impl MagicLinkToken {
+    /// Returns the raw token for the mail, or `None` when a link was sent moments ago.
+    pub fn issue(user_id: &String, device_name: Option<String>, db: &DBConPool) -> QueryResult<Option<String>> {
+        use crate::schema::magic_link_tokens::dsl;
+        
+        let conn = crate::get_db_connection(db);
+        let now = get_now_date_time();
+        
+        let last_issued_at = filter_for_active_tokens!(dsl::magic_link_tokens)
+            .select(dsl::created_at)
+            .filter(dsl::user_id.eq(user_id))
+            .order(dsl::created_at.desc())
+            .first::<chrono::NaiveDateTime>(&conn)
+            .optional()?;
+        
+        if is_resend_too_soon(last_issued_at) {
+            return Ok(None);
+        }
+        
+        let raw_token = generate_token(MAGIC_LINK_TOKEN_PREFIX);
+        
+        conn.transaction(|| {
+            Self::invalidate_by_user(user_id, &conn)?;
+            diesel::insert_into(dsl::magic_link_tokens)
+                .values(&InsertableMagicLinkToken {
+                    token: hash_token(&raw_token),
+                    user_id: user_id.clone(),
+                    device_name,
+                    expired_at: (now + chrono::Duration::minutes(MAGIC_LINK_VALIDITY_MINUTES)).naive_local(),
+                })
+                .execute(&conn)
+        })
+            .map(|_| Some(raw_token))
+    }
+    
+    /// Uses up the token and returns the credential with the device name given when the link was requested.
+    pub fn consume(token: &String, db: &DBConPool) -> Result<(UserCredential, Option<String>), ApiError> {
+        use crate::schema::magic_link_tokens::dsl;
+        
+        let conn = crate::get_db_connection(db);
+        
+        let magic_link = filter_for_active_tokens!(dsl::magic_link_tokens)
+            .select((dsl::token, dsl::user_id, dsl::device_name))
+            .filter(dsl::token.eq(hash_token(token)))
+            .first::<Self>(&conn)
+            .map_err(|_| ApiError::new(ApiErrorCode::InvalidToken, "Login link is invalid or expired."))?;
+        
+        let credential = UserCredential::fetch_by_id(&magic_link.user_id, db)
+            .ok()
+            .filter(|c| c.magic_link_enabled)
+            .ok_or_else(|| ApiError::new(ApiErrorCode::InvalidToken, "Login link is invalid or expired."))?;
+        
+        let marked_count = diesel::update(dsl::magic_link_tokens
+            .filter(dsl::token.eq(&magic_link.token))
+            .filter(dsl::deleted_at.is_null())
+        )
+            .set(dsl::deleted_at.eq(get_now_naive_date_time()))
+            .execute(&conn)
+            .map_err(|_| ApiError::new(ApiErrorCode::ServerError, "Failed to log in."))?;
+        
+        match marked_count {
+            0 => Err(ApiError::new(ApiErrorCode::InvalidToken, "Login link is invalid or expired.")),
+            _ => Ok((credential, magic_link.device_name)),
+        }
+    }
+    
+    /// Looks up used and expired links too, so that replaying an old link can be logged for its account.
+    pub fn fetch_user_id(token: &String, db: &DBConPool) -> QueryResult<String> {
+        use crate::schema::magic_link_tokens::dsl;
+        
+        dsl::magic_link_tokens
+            .select(dsl::user_id)
+            .filter(dsl::token.eq(hash_token(token)))
+            .first::<String>(&crate::get_db_connection(db))
+    }
+    
+    pub fn invalidate_by_user(user_id: &String, conn: &DBConnection) -> QueryResult<usize> {
+        use crate::schema::magic_link_tokens::dsl;
+        
+        diesel::update(dsl::magic_link_tokens.filter(dsl::user_id.eq(user_id)).filter(dsl::deleted_at.is_null()))
+            .set(dsl::deleted_at.eq(get_now_naive_date_time()))
+            .execute(conn)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,274 @@
+use diesel::prelude::*;
+use log::error;
+use validator::{Validate, ValidationError, ValidationErrors};
+
+use crate::{DBConnection, DBConPool};
+use crate::models::error::{ApiError, ApiErrorCode};
+use crate::models::get_now_naive_date_time;
+use crate::models::token_scope::*;
+use crate::models::user_session::UserSession;
+use crate::models::user_token::{generate_token, hash_token, UserToken};
+use crate::schema::{oauth_apps, oauth_authorization_codes, user_refresh_tokens, user_sessions, user_tokens};
+
+const CLIENT_SECRET_PREFIX: &str = "rsns_cs_";
+
+#[derive(Deserialize, Validate)]
+pub struct InputOAuthApp {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    #[validate(length(min = 1, max = 5), custom = "validate_redirect_uris")]
+    pub redirect_uris: Vec<String>,
+    #[serde(default)]
+    pub confidential: bool,
+}
+
+#[derive(Insertable)]
+#[table_name = "oauth_apps"]
+pub struct InsertableOAuthApp {
+    id: String,
+    owner_id: String,
+    name: String,
+    client_secret: Option<String>,
+    redirect_uris: String,
+}
+
+#[derive(Serialize, Queryable)]
+pub struct OAuthApp {
+    #[serde(rename = "client_id")]
+    pub id: String,
+    #[serde(skip)]
+    pub owner_id: String,
+    pub name: String,
+    #[serde(skip)]
+    pub client_secret: Option<String>,
+    #[serde(serialize_with = "serialize_redirect_uri_list")]
+    pub redirect_uris: String,
+    #[serde(serialize_with = "crate::models::serialize_naive_dt")]
+    pub created_at: chrono::NaiveDateTime,
+    #[serde(skip)]
+    pub updated_at: chrono::NaiveDateTime,
+    #[serde(skip)]
+    pub deleted_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Serialize)]
+pub struct RegisteredOAuthApp {
+    #[serde(flatten)]
+    app: OAuthApp,
+    client_secret: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct AuthorizedOAuthApp {
+    client_id: String,
+    name: String,
+    scopes: Vec<&'static str>,
+    #[serde(serialize_with = "crate::models::serialize_naive_dt")]
+    last_used_at: chrono::NaiveDateTime,
+}
+
+impl OAuthApp {
+    pub fn register(owner_id: &String, input: InputOAuthApp, db: &DBConPool) -> Result<Option<RegisteredOAuthApp>, ValidationErrors> {
+        use crate::schema::oauth_apps::dsl;
+        
+        input.validate()?;
+        
+        // Public clients such as mobile apps cannot keep a secret, so they rely on PKCE alone.
+        let raw_client_secret = input.confidential.then(|| generate_token(CLIENT_SECRET_PREFIX));
+        
+        let insertable_app = InsertableOAuthApp {
+            id: uuid::Uuid::new_v4().to_string(),
+            owner_id: owner_id.clone(),
+            name: input.name,
+            client_secret: raw_client_secret.as_ref().map(|s| hash_token(s)),
+            redirect_uris: input.redirect_uris.join(" "),
+        };
+        
+        let result = diesel::insert_into(dsl::oauth_apps)
+            .values(&insertable_app)
+            .execute(&crate::get_db_connection(db))
+            .and_then(|_| Self::fetch_by_id(&insertable_app.id, db));
+        
+        match result {
+            Ok(app) => Ok(Some(RegisteredOAuthApp { app, client_secret: raw_client_secret })),
+            Err(e) => {
+                error!("query was failed: {:?}", e);
+                Ok(None)
+            }
+        }
+    }
+    
+    pub fn fetch_by_id(app_id: &String, db: &DBConPool) -> QueryResult<Self> {
+        use crate::schema::oauth_apps::dsl;
+        
+        dsl::oauth_apps
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::id.eq(app_id))
+            .first::<Self>(&crate::get_db_connection(db))
+    }
+    
+    pub fn fetch_owned(app_id: &String, owner_id: &String, db: &DBConPool) -> QueryResult<Self> {
+        use crate::schema::oauth_apps::dsl;
+        
+        dsl::oauth_apps
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::id.eq(app_id))
+            .filter(dsl::owner_id.eq(owner_id))
+            .first::<Self>(&crate::get_db_connection(db))
+    }
+    
+    pub fn fetch_list_by_owner(owner_id: &String, db: &DBConPool) -> QueryResult<Vec<Self>> {
+        use crate::schema::oauth_apps::dsl;
+        
+        dsl::oauth_apps
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::owner_id.eq(owner_id))
+            .order(dsl::created_at.desc())
+            .load::<Self>(&crate::get_db_connection(db))
+    }
+    
+    pub fn fetch_authorized_list(user_id: &String, db: &DBConPool) -> QueryResult<Vec<AuthorizedOAuthApp>> {
+        let grants = user_sessions::table
+            .inner_join(oauth_apps::table.on(user_sessions::app_id.eq(oauth_apps::id.nullable())))
+            .select((oauth_apps::id, oauth_apps::name, user_sessions::scopes, user_sessions::last_used_at))
+            .filter(user_sessions::deleted_at.is_null())
+            .filter(user_sessions::expired_at.gt(get_now_naive_date_time()))
+            .filter(user_sessions::user_id.eq(user_id))
+            .filter(oauth_apps::deleted_at.is_null())
+            .order(user_sessions::last_used_at.desc())
+            .load::<(String, String, Option<String>, chrono::NaiveDateTime)>(&crate::get_db_connection(db))?;
+        
+        // An app can hold several grants, which are shown as one entry with their combined scopes.
+        let mut authorized_apps: Vec<AuthorizedOAuthApp> = vec![];
+        
+        for (client_id, name, scopes, last_used_at) in grants {
+            let scopes = parse_scope_list(scopes.as_deref().unwrap_or_default());
+            
+            match authorized_apps.iter_mut().find(|a| a.client_id == client_id) {
+                Some(app) => {
+                    for scope in scopes.iter().map(|s| s.as_str()) {
+                        if !app.scopes.contains(&scope) { app.scopes.push(scope); }
+                    }
+                }
+                None => authorized_apps.push(AuthorizedOAuthApp {
+                    client_id,
+                    name,
+                    scopes: scopes.iter().map(|s| s.as_str()).collect(),
+                    last_used_at,
+                }),
+            }
+        }
+        
+        Ok(authorized_apps)
+    }
+    
+    pub fn revoke_authorization(app_id: &String, user_id: &String, db: &DBConPool) -> Result<(), ApiError> {
+        let conn = crate::get_db_connection(db);
+        
+        match conn.transaction(|| UserSession::revoke_app_grants(app_id, Some(user_id), &conn)) {
+            Ok(count) if count > 0 => Ok(()),
+            Ok(_) => Err(ApiError::new(ApiErrorCode::NotFound, "App is not authorized.")),
+            Err(_) => Err(ApiError::new(ApiErrorCode::ServerError, "Failed to revoke app.")),
+        }
+    }
+    
+    pub fn has_redirect_uri(&self, redirect_uri: &str) -> bool {
+        self.redirect_uris.split_whitespace().any(|u| u == redirect_uri)
+    }
+    
+    pub fn verify_client_secret(&self, client_secret: Option<&String>) -> bool {
+        match (&self.client_secret, client_secret) {
+            (None, _) => true,
+            (Some(stored), Some(given)) => stored == &hash_token(given),
+            (Some(_), None) => false,
+        }
+    }
+    
+    pub fn revoke_token(&self, token: &String, db: &DBConPool) -> QueryResult<()> {
+        let conn = crate::get_db_connection(db);
+        let token_hash = hash_token(token);
+        
+        let family_id = user_tokens::table
+            .select(user_tokens::family_id)
+            .filter(user_tokens::token.eq(&token_hash))
+            .first::<String>(&conn)
+            .optional()?;
+        let family_id = match family_id {
+            Some(f) => Some(f),
+            None => user_refresh_tokens::table
+                .select(user_refresh_tokens::family_id)
+                .filter(user_refresh_tokens::token.eq(&token_hash))
+                .first::<String>(&conn)
+                .optional()?,
+        };
+        
+        // Tokens of other apps are left alone, as RFC 7009 asks for the request to be ignored.
+        let session_id = match family_id {
+            Some(f) => user_sessions::table
+                .select(user_sessions::id)
+                .filter(user_sessions::id.eq(f))
+                .filter(user_sessions::app_id.eq(&self.id))
+                .first::<String>(&conn)
+                .optional()?,
+            None => None,
+        };
+        
+        match session_id {
+            Some(s) => conn.transaction(|| UserToken::revoke_family(&s, &conn)),
+            None => Ok(()),
+        }
+    }
+    
+    pub fn delete(&self, db: &DBConPool) -> QueryResult<()> {
+        use crate::schema::oauth_apps::dsl;
+        
+        let conn = crate::get_db_connection(db);
+        
+        conn.transaction(|| {
+            UserSession::revoke_app_grants(&self.id, None, &conn)?;
+            diesel::update(dsl::oauth_apps.filter(dsl::id.eq(&self.id)))
+                .set(dsl::deleted_at.eq(get_now_naive_date_time()))
+                .execute(&conn)
+                .map(|_| ())
+        })
+    }
+    
+    pub fn purge_by_owner(owner_id: &String, conn: &DBConnection) -> QueryResult<()> {
+        use crate::schema::oauth_apps::dsl;
+        
+        let app_ids = dsl::oauth_apps
+            .select(dsl::id)
+            .filter(dsl::owner_id.eq(owner_id))
+            .load::<String>(conn)?;
+        
+        for app_id in &app_ids {
+            UserSession::revoke_app_grants(app_id, None, conn)?;
+        }
+        
+        diesel::delete(oauth_authorization_codes::table.filter(oauth_authorization_codes::app_id.eq_any(&app_ids)))
+            .execute(conn)?;
+        diesel::delete(dsl::oauth_apps.filter(dsl::owner_id.eq(owner_id)))
+            .execute(conn)
+            .map(|_| ())
+    }
+}
+
+fn validate_redirect_uris(redirect_uris: &Vec<String>) -> Result<(), ValidationError> {
+    let is_valid = |uri: &String| {
+        uri.len() <= 190 && !uri.contains(char::is_whitespace)
+            && url::Url::parse(uri).map_or(false, |u| u.fragment().is_none() && !u.cannot_be_a_base())
+    };
+    
+    if !redirect_uris.iter().all(is_valid) {
+        return Err(ValidationError::new("invalid_redirect_uri"));
+    }
+    
+    Ok(())
+}
+
+fn serialize_redirect_uri_list<S>(redirect_uris: &String, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+{
+    serializer.collect_seq(redirect_uris.split_whitespace())
+}
@@ -0,0 +1,216 @@
+use diesel::prelude::*;
+use sha2::{Digest, Sha256};
+
+use crate::DBConPool;
+use crate::models::{get_now_date_time, get_now_naive_date_time};
+use crate::models::error::{ApiError, ApiErrorCode, OAuthError};
+use crate::models::oauth_app::OAuthApp;
+use crate::models::token_scope::*;
+use crate::models::user_session::{SessionGrant, SessionMetadata};
+use crate::models::user_token::{generate_token, hash_token, IssuedUserToken, UserToken};
+use crate::schema::oauth_authorization_codes;
+
+const AUTHORIZATION_CODE_PREFIX: &str = "rsns_ac_";
+const AUTHORIZATION_CODE_VALIDITY_MINUTES: i64 = 10;
+const CODE_CHALLENGE_METHOD: &str = "S256";
+
+#[derive(Deserialize)]
+pub struct InputAuthorizationRequest {
+    pub response_type: String,
+    pub client_id: String,
+    pub redirect_uri: String,
+    pub scope: Option<String>,
+    pub state: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+    #[serde(default)]
+    pub approved: bool,
+}
+
+#[derive(Deserialize)]
+pub struct InputTokenRequest {
+    pub grant_type: String,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    pub code: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub code_verifier: Option<String>,
+    pub refresh_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct InputRevocationRequest {
+    pub token: String,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+#[derive(Insertable)]
+#[table_name = "oauth_authorization_codes"]
+pub struct InsertableOAuthAuthorizationCode {
+    code: String,
+    app_id: String,
+    user_id: String,
+    redirect_uri: String,
+    scopes: String,
+    code_challenge: String,
+    expired_at: chrono::NaiveDateTime,
+}
+
+#[derive(Queryable)]
+pub struct OAuthAuthorizationCode {
+    pub code: String,
+    pub user_id: String,
+    pub redirect_uri: String,
+    pub scopes: String,
+    pub code_challenge: String,
+    pub session_id: Option<String>,
+    pub used_at: Option<chrono::NaiveDateTime>,
+}
+
+impl InputAuthorizationRequest {
+    pub fn validate_for_app(&self, db: &DBConPool) -> Result<(OAuthApp, Vec<TokenScope>), ApiError> {
+        let app = OAuthApp::fetch_by_id(&self.client_id, db)
+            .map_err(|_| ApiError::new(ApiErrorCode::NotFound, "App not found."))?;
+        
+        if !app.has_redirect_uri(&self.redirect_uri) {
+            return Err(ApiError::new(ApiErrorCode::InvalidRequest, "redirect_uri is not registered for this app."));
+        }
+        
+        if self.response_type != "code" {
+            return Err(ApiError::new(ApiErrorCode::InvalidRequest, "Only the code response_type is supported."));
+        }
+        
+        let has_valid_challenge = self.code_challenge.as_ref().map_or(false, |c| (43..=128).contains(&c.len()));
+        
+        if !has_valid_challenge || self.code_challenge_method.as_deref() != Some(CODE_CHALLENGE_METHOD) {
+            return Err(ApiError::new(ApiErrorCode::InvalidRequest, "PKCE with the S256 method is required."));
+        }
+        
+        let requested_scopes = self.scope.as_deref().unwrap_or(TokenScope::Read.as_str());
+        
+        if requested_scopes.split_whitespace().any(|s| TokenScope::parse_grantable(s).is_none()) {
+            return Err(ApiError::new(ApiErrorCode::InvalidRequest, "Unknown scope."));
+        }
+        
+        match parse_scope_list(requested_scopes) {
+            scopes if scopes.is_empty() => Err(ApiError::new(ApiErrorCode::InvalidRequest, "No scope was requested.")),
+            scopes => Ok((app, scopes)),
+        }
+    }
+}
+
+impl OAuthAuthorizationCode {
+    pub fn issue(request: &InputAuthorizationRequest, scopes: &[TokenScope], user_id: &String, db: &DBConPool) -> QueryResult<String> {
+        use crate::schema::oauth_authorization_codes::dsl;
+        
+        let raw_code = generate_token(AUTHORIZATION_CODE_PREFIX);
+        
+        diesel::insert_into(dsl::oauth_authorization_codes)
+            .values(&InsertableOAuthAuthorizationCode {
+                code: hash_token(&raw_code),
+                app_id: request.client_id.clone(),
+                user_id: user_id.clone(),
+                redirect_uri: request.redirect_uri.clone(),
+                scopes: join_scope_list(scopes),
+                code_challenge: request.code_challenge.clone().unwrap_or_default(),
+                expired_at: (get_now_date_time() + chrono::Duration::minutes(AUTHORIZATION_CODE_VALIDITY_MINUTES)).naive_local(),
+            })
+            .execute(&crate::get_db_connection(db))
+            .map(|_| raw_code)
+    }
+    
+    pub fn exchange(app: &OAuthApp, request: &InputTokenRequest, metadata: &SessionMetadata, db: &DBConPool) -> Result<(IssuedUserToken, Vec<TokenScope>), OAuthError> {
+        use crate::schema::oauth_authorization_codes::dsl;
+        
+        let (code, redirect_uri, code_verifier) = match (&request.code, &request.redirect_uri, &request.code_verifier) {
+            (Some(c), Some(r), Some(v)) => (c, r, v),
+            _ => return Err(OAuthError::new("invalid_request", "code, redirect_uri and code_verifier are required.")),
+        };
+        
+        let conn = crate::get_db_connection(db);
+        
+        let authorization_code = dsl::oauth_authorization_codes
+            .select((dsl::code, dsl::user_id, dsl::redirect_uri, dsl::scopes, dsl::code_challenge, dsl::session_id, dsl::used_at))
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::expired_at.gt(get_now_naive_date_time()))
+            .filter(dsl::code.eq(hash_token(code)))
+            .filter(dsl::app_id.eq(&app.id))
+            .first::<Self>(&conn)
+            .map_err(|_| OAuthError::new("invalid_grant", "Authorization code is invalid or expired."))?;
+        
+        // A replayed code may have been intercepted, so the tokens issued for it are revoked.
+        if authorization_code.used_at.is_some() {
+            if let Some(session_id) = &authorization_code.session_id {
+                conn.transaction(|| UserToken::revoke_family(session_id, &conn))
+                    .map_err(|_| OAuthError::new("server_error", "Failed to revoke tokens."))?;
+            }
+            return Err(OAuthError::new("invalid_grant", "Authorization code was already used."));
+        }
+        
+        if &authorization_code.redirect_uri != redirect_uri || !verify_code_challenge(&authorization_code.code_challenge, code_verifier) {
+            return Err(OAuthError::new("invalid_grant", "redirect_uri or code_verifier does not match."));
+        }
+        
+        let grant = SessionGrant { app_id: app.id.clone(), scopes: parse_scope_list(&authorization_code.scopes) };
+        
+        let result = conn.transaction::<_, diesel::result::Error, _>(|| {
+            let marked_count = diesel::update(dsl::oauth_authorization_codes
+                .filter(dsl::code.eq(&authorization_code.code))
+                .filter(dsl::used_at.is_null())
+            )
+                .set(dsl::used_at.eq(get_now_naive_date_time()))
+                .execute(&conn)?;
+            
+            if marked_count == 0 {
+                return Ok(None);
+            }
+            
+            let issued_token = UserToken::issue_for_session(&authorization_code.user_id, metadata, Some(&grant), &conn)?;
+            
+            diesel::update(dsl::oauth_authorization_codes.filter(dsl::code.eq(&authorization_code.code)))
+                .set(dsl::session_id.eq(&issued_token.session_id))
+                .execute(&conn)?;
+            
+            Ok(Some(issued_token))
+        });
+        
+        match result {
+            Ok(Some(t)) => Ok((t, grant.scopes)),
+            Ok(None) => Err(OAuthError::new("invalid_grant", "Authorization code was already used.")),
+            Err(_) => Err(OAuthError::new("server_error", "Failed to issue token.")),
+        }
+    }
+}
+
+fn verify_code_challenge(code_challenge: &str, code_verifier: &str) -> bool {
+    if !(43..=128).contains(&code_verifier.len()) {
+        return false;
+    }
+    
+    let digest = Sha256::digest(code_verifier.as_bytes());
+    
+    base64::encode_config(digest, base64::URL_SAFE_NO_PAD) == code_challenge
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    // The S256 example of RFC 7636 Appendix B.
+    const RFC7636_VERIFIER: &str = "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+    const RFC7636_CHALLENGE: &str = "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM";
+    
+    #[test]
+    fn verify_code_challenge_matches_rfc7636_example() {
+        assert!(verify_code_challenge(RFC7636_CHALLENGE, RFC7636_VERIFIER));
+    }
+    
+    #[test]
+    fn verify_code_challenge_rejects_other_verifiers() {
+        let other_verifier = RFC7636_VERIFIER.replace('d', "e");
+        
+        assert!(!verify_code_challenge(RFC7636_CHALLENGE, &other_verifier));
+        assert!(!verify_code_challenge(RFC7636_CHALLENGE, &RFC7636_VERIFIER[..42]));
+    }
+}
\ No newline at end of file
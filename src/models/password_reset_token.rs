@@ -0,0 +1,129 @@
+use diesel::prelude::*;
+use validator::Validate;
+
+use crate::{DBConnection, DBConPool};
+use crate::models::{get_now_date_time, get_now_naive_date_time, is_resend_too_soon};
+use crate::models::error::{ApiError, ApiErrorCode};
+use crate::models::personal_access_token::PersonalAccessToken;
+use crate::models::user_credential::UserCredential;
+use crate::models::user_token::{generate_token, hash_token, UserToken};
+use crate::schema::password_reset_tokens;
+
+const PASSWORD_RESET_TOKEN_PREFIX: &str = "rsns_pr_";
+const PASSWORD_RESET_VALIDITY_MINUTES: i64 = 30;
+
+#[derive(Deserialize)]
+pub struct InputForgotPassword {
+    pub email: String,
+}
+
+#[derive(Deserialize, Validate)]
+pub struct InputPasswordReset {
+    pub token: String,
+    #[validate(length(min = 8))]
+    #[serde(rename = "password")]
+    pub raw_password: String,
+}
+
+#[derive(Insertable)]
+#[table_name = "password_reset_tokens"]
+pub struct InsertablePasswordResetToken {
+    token: String,
+    user_id: String,
+    expired_at: chrono::NaiveDateTime,
+}
+
+#[derive(Queryable)]
+pub struct PasswordResetToken {
+    pub token: String,
+    pub user_id: String,
+}
+
+impl PasswordResetToken {
+    /// Returns the raw token for the mail, or `None` when a token was sent moments ago.
+    pub fn issue(user_id: &String, db: &DBConPool) -> QueryResult<Option<String>> {
+        use crate::schema::password_reset_tokens::dsl;
+        
+        let conn = crate::get_db_connection(db);
+        let now = get_now_date_time();
+        
+        let last_issued_at = filter_for_active_tokens!(dsl::password_reset_tokens)
+            .select(dsl::created_at)
+            .filter(dsl::user_id.eq(user_id))
+            .order(dsl::created_at.desc())
+            .first::<chrono::NaiveDateTime>(&conn)
+            .optional()?;
+        
+        if is_resend_too_soon(last_issued_at) {
+            return Ok(None);
+        }
+        
+        let raw_token = generate_token(PASSWORD_RESET_TOKEN_PREFIX);
+        
+        conn.transaction(|| {
+            Self::invalidate_by_user(user_id, &conn)?;
+            diesel::insert_into(dsl::password_reset_tokens)
+                .values(&InsertablePasswordResetToken {
+                    token: hash_token(&raw_token),
+                    user_id: user_id.clone(),
+                    expired_at: (now + chrono::Duration::minutes(PASSWORD_RESET_VALIDITY_MINUTES)).naive_local(),
+                })
+                .execute(&conn)
+        })
+            .map(|_| Some(raw_token))
+    }
+    
+    /// Returns the id of the user whose password was reset.
+    pub fn reset(input: InputPasswordReset, db: &DBConPool) -> Result<String, ApiError> {
+        use crate::schema::password_reset_tokens::dsl;
+        
+        if let Err(_) = input.validate() {
+            return Err(ApiError::new(ApiErrorCode::InvalidRequest, "Invalid parameter."));
+        }
+        
+        let conn = crate::get_db_connection(db);
+        
+        let reset_token = filter_for_active_tokens!(dsl::password_reset_tokens)
+            .select((dsl::token, dsl::user_id))
+            .filter(dsl::token.eq(hash_token(&input.token)))
+            .first::<Self>(&conn)
+            .map_err(|_| ApiError::new(ApiErrorCode::InvalidToken, "Reset token is invalid or expired."))?;
+        
+        // Whoever held the old password may still be logged in or hold a personal access token, so all of them end with the reset.
+        let result = conn.transaction::<_, diesel::result::Error, _>(|| {
+            let marked_count = diesel::update(dsl::password_reset_tokens
+                .filter(dsl::token.eq(&reset_token.token))
+                .filter(dsl::deleted_at.is_null())
+            )
+                .set(dsl::deleted_at.eq(get_now_naive_date_time()))
+                .execute(&conn)?;
+            
+            if marked_count == 0 {
+                return Ok(false);
+            }
+            
+            Self::invalidate_by_user(&reset_token.user_id, &conn)?;
+            if UserCredential::set_password(&reset_token.user_id, &input.raw_password, &conn)? == 0 {
+                return Ok(false);
+            }
+            UserToken::revoke_all(&reset_token.user_id, &conn)?;
+            PersonalAccessToken::revoke_all(&reset_token.user_id, &conn)?;
+            
+            Ok(true)
+        });
+        
+        match result {
+            Ok(true) => Ok(reset_token.user_id),
+            Ok(false) => Err(ApiError::new(ApiErrorCode::InvalidToken, "Reset token is invalid or expired.")),
+            Err(_) => Err(ApiError::new(ApiErrorCode::ServerError, "Failed to reset password.")),
+        }
+    }
+    
+    fn invalidate_by_user(user_id: &String, conn: &DBConnection) -> QueryResult<usize> {
+        use crate::schema::password_reset_tokens::dsl;
+        
+        diesel::update(dsl::password_reset_tokens.filter(dsl::user_id.eq(user_id)).filter(dsl::deleted_at.is_null()))
+            .set(dsl::deleted_at.eq(get_now_naive_date_time()))
+            .execute(conn)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,180 @@
+use diesel::prelude::*;
+use log::error;
+use validator::{Validate, ValidationError, ValidationErrors};
+
+use crate::{DBConnection, DBConPool};
+use crate::models::{get_now_date_time, get_now_naive_date_time};
+use crate::models::error::{ApiError, ApiErrorCode};
+use crate::models::token_scope::*;
+use crate::models::user_token::{generate_token, hash_token};
+use crate::schema::personal_access_tokens;
+
+pub const PERSONAL_ACCESS_TOKEN_PREFIX: &str = "rsns_pat_";
+const LAST_USED_UPDATE_INTERVAL_MINUTES: i64 = 1;
+
+#[derive(Deserialize, Validate)]
+pub struct InputPersonalAccessToken {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    #[validate(length(min = 1), custom = "validate_scopes")]
+    pub scopes: Vec<String>,
+    #[validate(range(min = 1, max = 365))]
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Insertable)]
+#[table_name = "personal_access_tokens"]
+pub struct InsertablePersonalAccessToken {
+    id: String,
+    user_id: String,
+    name: String,
+    token: String,
+    scopes: String,
+    expired_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Serialize, Queryable)]
+pub struct PersonalAccessToken {
+    pub id: String,
+    #[serde(skip)]
+    pub user_id: String,
+    pub name: String,
+    #[serde(skip)]
+    pub token: String,
+    #[serde(serialize_with = "serialize_scope_list")]
+    pub scopes: String,
+    #[serde(serialize_with = "crate::models::serialize_option_naive_dt")]
+    pub last_used_at: Option<chrono::NaiveDateTime>,
+    #[serde(serialize_with = "crate::models::serialize_option_naive_dt")]
+    pub expired_at: Option<chrono::NaiveDateTime>,
+    #[serde(serialize_with = "crate::models::serialize_naive_dt")]
+    pub created_at: chrono::NaiveDateTime,
+    #[serde(skip)]
+    pub updated_at: chrono::NaiveDateTime,
+    #[serde(skip)]
+    pub deleted_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Serialize)]
+pub struct IssuedPersonalAccessToken {
+    #[serde(flatten)]
+    personal_access_token: PersonalAccessToken,
+    token: String,
+}
+
+macro_rules! filter_for_active_tokens {
+    ($query:expr) => {
+        $query
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::expired_at.is_null().or(dsl::expired_at.gt(get_now_naive_date_time())))
+    }
+}
+
+impl PersonalAccessToken {
+    pub fn issue(user_id: &String, input: InputPersonalAccessToken, db: &DBConPool) -> Result<Option<IssuedPersonalAccessToken>, ValidationErrors> {
+        use crate::schema::personal_access_tokens::dsl;
+        
+        input.validate()?;
+        
+        let raw_token = generate_token(PERSONAL_ACCESS_TOKEN_PREFIX);
+        let scopes = input.scopes.iter().filter_map(|s| TokenScope::parse_grantable(s)).collect::<Vec<TokenScope>>();
+        
+        let insertable_token = InsertablePersonalAccessToken {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: user_id.clone(),
+            name: input.name,
+            token: hash_token(&raw_token),
+            scopes: join_scope_list(&scopes),
+            expired_at: input.expires_in_days.map(|d| (get_now_date_time() + chrono::Duration::days(d)).naive_local()),
+        };
+        
+        let result = diesel::insert_into(dsl::personal_access_tokens)
+            .values(&insertable_token)
+            .execute(&crate::get_db_connection(db))
+            .and_then(|_| Self::fetch_by_id(&insertable_token.id, user_id, db));
+        
+        match result {
+            Ok(t) => Ok(Some(IssuedPersonalAccessToken { personal_access_token: t, token: raw_token })),
+            Err(e) => {
+                error!("query was failed: {:?}", e);
+                Ok(None)
+            }
+        }
+    }
+    
+    pub fn fetch_by_id(token_id: &String, user_id: &String, db: &DBConPool) -> QueryResult<Self> {
+        use crate::schema::personal_access_tokens::dsl;
+        
+        filter_for_active_tokens!(dsl::personal_access_tokens)
+            .filter(dsl::id.eq(token_id))
+            .filter(dsl::user_id.eq(user_id))
+            .first::<Self>(&crate::get_db_connection(db))
+    }
+    
+    pub fn fetch_list_by_user(user_id: &String, db: &DBConPool) -> QueryResult<Vec<Self>> {
+        use crate::schema::personal_access_tokens::dsl;
+        
+        filter_for_active_tokens!(dsl::personal_access_tokens)
+            .filter(dsl::user_id.eq(user_id))
+            .order(dsl::created_at.desc())
+            .load::<Self>(&crate::get_db_connection(db))
+    }
+    
+    pub fn verify_token(token: &String, db: &DBConPool) -> Result<Self, ApiError> {
+        use crate::schema::personal_access_tokens::dsl;
+        
+        let conn = crate::get_db_connection(db);
+        
+        let personal_access_token = filter_for_active_tokens!(dsl::personal_access_tokens)
+            .filter(dsl::token.eq(hash_token(token)))
+            .first::<Self>(&conn)
+            .map_err(|_| ApiError::new(ApiErrorCode::InvalidToken, "Invalid token."))?;
+        
+        let now = get_now_date_time();
+        let last_used_threshold = (now - chrono::Duration::minutes(LAST_USED_UPDATE_INTERVAL_MINUTES)).naive_local();
+        
+        if personal_access_token.last_used_at.map_or(true, |t| t < last_used_threshold) {
+            diesel::update(dsl::personal_access_tokens.filter(dsl::id.eq(&personal_access_token.id)))
+                .set(dsl::last_used_at.eq(now.naive_local()))
+                .execute(&conn)
+                .map_err(|_| ApiError::new(ApiErrorCode::ServerError, "Failed to verify token."))?;
+        }
+        
+        Ok(personal_access_token)
+    }
+    
+    pub fn scope_list(&self) -> Vec<TokenScope> {
+        parse_scope_list(&self.scopes)
+    }
+    
+    pub fn revoke(&self, db: &DBConPool) -> QueryResult<usize> {
+        use crate::schema::personal_access_tokens::dsl;
+        
+        diesel::update(dsl::personal_access_tokens.filter(dsl::id.eq(&self.id)))
+            .set(dsl::deleted_at.eq(get_now_naive_date_time()))
+            .execute(&crate::get_db_connection(db))
+    }
+    
+    pub fn revoke_all(user_id: &String, conn: &DBConnection) -> QueryResult<usize> {
+        use crate::schema::personal_access_tokens::dsl;
+        
+        diesel::update(dsl::personal_access_tokens.filter(dsl::user_id.eq(user_id)).filter(dsl::deleted_at.is_null()))
+            .set(dsl::deleted_at.eq(get_now_naive_date_time()))
+            .execute(conn)
+    }
+}
+
+fn validate_scopes(scopes: &Vec<String>) -> Result<(), ValidationError> {
+    if scopes.iter().any(|s| TokenScope::parse_grantable(s).is_none()) {
+        return Err(ValidationError::new("unknown_scope"));
+    }
+    
+    Ok(())
+}
+
+fn serialize_scope_list<S>(scopes: &String, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+{
+    serializer.collect_seq(scopes.split_whitespace())
+}
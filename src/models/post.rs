@@ -1,14 +1,17 @@
+use actix_web::http::header::EntityTag;
 use diesel::prelude::*;
 use log::error;
 use serde::Serialize;
 use validator::{Validate, ValidationErrors};
 
 use crate::DBConPool;
-use crate::models::get_now_naive_date_time;
+use crate::models::{escape_like_pattern, get_now_naive_date_time};
+use crate::models::post_search::PostSearchQuery;
 use crate::models::user::{FilteredUser, User};
 use crate::schema::posts;
 
 const POST_LIST_LIMIT_COUNT: i64 = 10;
+const POST_SEARCH_LIMIT_COUNT: i64 = 20;
 
 #[derive(Deserialize, Validate)]
 pub struct InputPost {
@@ -17,6 +20,12 @@ pub struct InputPost {
     pub is_publish: bool,
 }
 
+pub struct ImportedPost {
+    pub source_id: String,
+    pub content: String,
+    pub published_at: Option<chrono::NaiveDateTime>,
+}
+
 #[derive(Insertable, Deserialize)]
 #[table_name = "posts"]
 pub struct InsertablePost {
@@ -24,6 +33,7 @@ pub struct InsertablePost {
     pub content: String,
     pub author_id: String,
     pub published_at: Option<chrono::NaiveDateTime>,
+    pub import_source_id: Option<String>,
 }
 
 impl InsertablePost {
@@ -35,6 +45,19 @@ impl InsertablePost {
             content: new_post.content,
             author_id: author.id.clone(),
             published_at: new_post.is_publish.then(|| get_now_naive_date_time()),
+            import_source_id: None,
+        })
+    }
+    
+    pub fn new_imported(imported_post: ImportedPost, author: &User) -> Result<Self, ValidationErrors> {
+        InputPost { content: imported_post.content.clone(), is_publish: imported_post.published_at.is_some() }.validate()?;
+        
+        Ok(Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            content: imported_post.content,
+            author_id: author.id.clone(),
+            published_at: imported_post.published_at,
+            import_source_id: Some(imported_post.source_id),
         })
     }
 }
@@ -69,6 +92,14 @@ pub struct Post {
     pub published_at: Option<chrono::NaiveDateTime>,
     #[serde(skip)]
     pub deleted_at: Option<chrono::NaiveDateTime>,
+    #[serde(skip)]
+    pub import_source_id: Option<String>,
+}
+
+impl PostWithUser {
+    pub fn entity_tag(&self) -> EntityTag {
+        EntityTag::strong(format!("{}-{}", self.post.entity_tag().tag(), self.user.entity_tag().tag()))
+    }
 }
 
 impl Posts {
@@ -86,6 +117,10 @@ impl Post {
         PostTagged::Post(self)
     }
     
+    pub fn entity_tag(&self) -> EntityTag {
+        EntityTag::strong(format!("{}-{}", self.id, self.updated_at.timestamp()))
+    }
+    
     pub fn with_user(self, user: FilteredUser) -> PostWithUser {
         PostWithUser { user, post: self }
     }
@@ -102,6 +137,25 @@ impl Post {
         response_item_insertion_result!(modified_rows_count, insertable_post.id)
     }
     
+    pub fn insert_imported(post: ImportedPost, author_user: &User, db: &DBConPool) -> Result<Option<bool>, ValidationErrors> {
+        use crate::schema::posts::dsl;
+        
+        let insertable_post = InsertablePost::new_imported(post, author_user)?;
+        
+        // Rows that were already imported hit `author_import_source_index` and are skipped.
+        let modified_rows_count = diesel::insert_or_ignore_into(dsl::posts)
+            .values(&insertable_post)
+            .execute(&crate::get_db_connection(db));
+        
+        match modified_rows_count {
+            Ok(count) => Ok(Some(count > 0)),
+            Err(e) => {
+                error!("query was failed: {:?}", e);
+                Ok(None)
+            }
+        }
+    }
+    
     pub fn fetch_by_id(post_id: &String, db: &DBConPool) -> QueryResult<Post> {
         use crate::schema::posts::dsl;
         
@@ -149,6 +203,16 @@ impl Post {
             })
     }
     
+    pub fn fetch_all_by_author(author_id: &String, db: &DBConPool) -> QueryResult<Vec<Post>> {
+        use crate::schema::posts::dsl;
+        
+        dsl::posts
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::author_id.eq(author_id))
+            .order(dsl::created_at.asc())
+            .load::<Post>(&crate::get_db_connection(db))
+    }
+    
     pub fn fetch_draft_list_by_author(author: &User, db: &DBConPool) -> QueryResult<Vec<Post>> {
         use crate::schema::posts::dsl;
         
@@ -158,4 +222,99 @@ impl Post {
             .order(dsl::published_at.desc())
             .load::<Post>(&crate::get_db_connection(db))
     }
+    
+    pub fn search(search_query: &PostSearchQuery, page: i64, db: &DBConPool) -> QueryResult<Posts> {
+        use diesel::dsl::sql;
+        use diesel::sql_types::{Bool, Text};
+        use crate::schema::posts::dsl;
+        use crate::schema::users;
+        
+        let mut query = dsl::posts
+            .inner_join(users::table)
+            .into_boxed()
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::published_at.is_not_null().and(dsl::published_at.lt(get_now_naive_date_time())))
+            .filter(users::deactivated_at.is_null());
+        
+        if let Some(expression) = search_query.full_text_expression() {
+            query = query.filter(
+                sql::<Bool>("MATCH (posts.content) AGAINST (").bind::<Text, _>(expression).sql(" IN BOOLEAN MODE)")
+            );
+        }
+        
+        for hashtag in &search_query.hashtags {
+            // LIKE narrows the rows cheaply, REGEXP then rejects longer tags such as `#tagged` for `#tag`.
+            query = query
+                .filter(dsl::content.like(format!("%{}%", escape_like_pattern(hashtag))))
+                .filter(sql::<Bool>("posts.content REGEXP ").bind::<Text, _>(hashtag_pattern(hashtag)));
+        }
+        
+        if let Some(handle) = &search_query.from {
+            query = query.filter(users::id_name.eq(handle));
+        }
+        
+        if let Some(since) = search_query.since {
+            query = query.filter(dsl::published_at.ge(since.and_hms(0, 0, 0)));
+        }
+        
+        if let Some(until) = search_query.until {
+            // The last representable date has no upper bound to apply.
+            if let Some(next_day) = until.succ_opt() {
+                query = query.filter(dsl::published_at.lt(next_day.and_hms(0, 0, 0)));
+            }
+        }
+        
+        query
+            .order(dsl::published_at.desc())
+            .limit(POST_SEARCH_LIMIT_COUNT)
+            .offset(page * POST_SEARCH_LIMIT_COUNT)
+            .load::<(Post, User)>(&crate::get_db_connection(db))
+            .map(|rows: Vec<(Post, User)>| {
+                Posts::new(
+                    rows.into_iter().map(|(p, u)| p.with_user(u.filter_for_response())).collect::<Vec<PostWithUser>>()
+                )
+            })
+    }
+}
+
+fn hashtag_pattern(hashtag: &str) -> String {
+    let escaped = hashtag.chars().fold(String::with_capacity(hashtag.len()), |mut escaped, c| {
+        if matches!(c, '\\' | '.' | '^' | '$' | '|' | '?' | '*' | '+' | '(' | ')' | '[' | ']' | '{' | '}') { escaped.push('\\'); }
+        escaped.push(c);
+        escaped
+    });
+    
+    format!("(^|[^[:alnum:]_#]){}([^[:alnum:]_]|$)", escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use regex::Regex;
+    
+    use super::*;
+    
+    fn matches_hashtag(hashtag: &str, content: &str) -> bool {
+        Regex::new(&hashtag_pattern(hashtag)).expect("pattern should compile").is_match(content)
+    }
+    
+    #[test]
+    fn hashtag_pattern_matches_whole_tags() {
+        assert!(matches_hashtag("#tag", "#tag"));
+        assert!(matches_hashtag("#tag", "hello #tag world"));
+        assert!(matches_hashtag("#tag", "hello #tag, world"));
+    }
+    
+    #[test]
+    fn hashtag_pattern_rejects_longer_tags() {
+        assert!(!matches_hashtag("#tag", "#tagged"));
+        assert!(!matches_hashtag("#tag", "#tag_line"));
+        assert!(!matches_hashtag("#tag", "##tag"));
+        assert!(!matches_hashtag("#tag", "not#tag"));
+    }
+    
+    #[test]
+    fn hashtag_pattern_escapes_metacharacters() {
+        assert!(matches_hashtag("#c++", "learning #c++ today"));
+        assert!(!matches_hashtag("#a.b", "#axb"));
+    }
 }
\ No newline at end of file
@@ -0,0 +1,151 @@
+use diesel::prelude::*;
+
+use crate::DBConPool;
+use crate::schema::{post_import_errors, post_imports};
+
+pub const FORMAT_ARCHIVE: &str = "archive";
+pub const FORMAT_JSON: &str = "json";
+pub const FORMAT_CSV: &str = "csv";
+
+pub const STATUS_PENDING: &str = "pending";
+pub const STATUS_PROCESSING: &str = "processing";
+pub const STATUS_COMPLETED: &str = "completed";
+pub const STATUS_FAILED: &str = "failed";
+
+#[derive(Insertable)]
+#[table_name = "post_imports"]
+pub struct InsertablePostImport {
+    id: String,
+    user_id: String,
+    format: String,
+    status: String,
+}
+
+impl InsertablePostImport {
+    fn new(user_id: &String, format: &str) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: user_id.clone(),
+            format: format.to_string(),
+            status: STATUS_PENDING.to_string(),
+        }
+    }
+}
+
+#[derive(Insertable)]
+#[table_name = "post_import_errors"]
+pub struct InsertablePostImportError {
+    id: String,
+    import_id: String,
+    row_number: i32,
+    message: String,
+}
+
+#[derive(AsChangeset, Default)]
+#[table_name = "post_imports"]
+pub struct PostImportProgress {
+    pub total_count: i32,
+    pub imported_count: i32,
+    pub skipped_count: i32,
+    pub failed_count: i32,
+}
+
+#[derive(Serialize, Queryable, Clone)]
+pub struct PostImport {
+    pub id: String,
+    #[serde(skip)]
+    pub user_id: String,
+    pub format: String,
+    pub status: String,
+    pub total_count: i32,
+    pub imported_count: i32,
+    pub skipped_count: i32,
+    pub failed_count: i32,
+    #[serde(serialize_with = "crate::models::serialize_naive_dt")]
+    pub created_at: chrono::NaiveDateTime,
+    #[serde(serialize_with = "crate::models::serialize_naive_dt")]
+    pub updated_at: chrono::NaiveDateTime,
+    #[serde(skip)]
+    pub deleted_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Serialize, Queryable)]
+pub struct PostImportError {
+    pub row_number: i32,
+    pub message: String,
+}
+
+#[derive(Serialize)]
+pub struct PostImportWithErrors {
+    import: PostImport,
+    errors: Vec<PostImportError>,
+}
+
+impl PostImport {
+    pub fn is_supported_format(format: &str) -> bool {
+        matches!(format, FORMAT_ARCHIVE | FORMAT_JSON | FORMAT_CSV)
+    }
+    
+    pub fn request(user_id: &String, format: &str, db: &DBConPool) -> QueryResult<Self> {
+        use crate::schema::post_imports::dsl;
+        
+        let insertable_import = InsertablePostImport::new(user_id, format);
+        
+        diesel::insert_into(dsl::post_imports)
+            .values(&insertable_import)
+            .execute(&crate::get_db_connection(db))
+            .and_then(|_| Self::fetch_by_id(&insertable_import.id, user_id, db))
+    }
+    
+    pub fn fetch_by_id(import_id: &String, user_id: &String, db: &DBConPool) -> QueryResult<Self> {
+        use crate::schema::post_imports::dsl;
+        
+        dsl::post_imports
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::id.eq(import_id))
+            .filter(dsl::user_id.eq(user_id))
+            .first::<Self>(&crate::get_db_connection(db))
+    }
+    
+    pub fn with_errors(self, db: &DBConPool) -> QueryResult<PostImportWithErrors> {
+        use crate::schema::post_import_errors::dsl;
+        
+        dsl::post_import_errors
+            .select((dsl::row_number, dsl::message))
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::import_id.eq(&self.id))
+            .order(dsl::row_number.asc())
+            .load::<PostImportError>(&crate::get_db_connection(db))
+            .map(|errors| PostImportWithErrors { import: self, errors })
+    }
+    
+    pub fn update_progress(&self, progress: &PostImportProgress, status: &str, db: &DBConPool) -> QueryResult<usize> {
+        use crate::schema::post_imports::dsl;
+        
+        diesel::update(dsl::post_imports.filter(dsl::id.eq(&self.id)))
+            .set((progress, dsl::status.eq(status)))
+            .execute(&crate::get_db_connection(db))
+    }
+    
+    /// Keeps the progress made so far, which is all that is known when the job was aborted.
+    pub fn fail(&self, db: &DBConPool) -> QueryResult<usize> {
+        use crate::schema::post_imports::dsl;
+        
+        diesel::update(dsl::post_imports.filter(dsl::id.eq(&self.id)))
+            .set(dsl::status.eq(STATUS_FAILED))
+            .execute(&crate::get_db_connection(db))
+    }
+    
+    pub fn record_error(&self, row_number: i32, message: &str, db: &DBConPool) -> QueryResult<usize> {
+        use crate::schema::post_import_errors::dsl;
+        
+        diesel::insert_into(dsl::post_import_errors)
+            .values(&InsertablePostImportError {
+                id: uuid::Uuid::new_v4().to_string(),
+                import_id: self.id.clone(),
+                row_number,
+                message: message.chars().take(255).collect(),
+            })
+            .execute(&crate::get_db_connection(db))
+    }
+}
@@ -0,0 +1,158 @@
+use crate::models::error::{ApiError, ApiErrorCode};
+
+const SEARCH_DATE_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Default)]
+pub struct PostSearchQuery {
+    pub terms: Vec<String>,
+    pub phrases: Vec<String>,
+    pub hashtags: Vec<String>,
+    pub from: Option<String>,
+    pub since: Option<chrono::NaiveDate>,
+    pub until: Option<chrono::NaiveDate>,
+}
+
+impl PostSearchQuery {
+    pub fn parse(query: &str) -> Result<Self, ApiError> {
+        let mut search_query = Self::default();
+        
+        for (token, is_quoted) in tokenize(query) {
+            if is_quoted {
+                search_query.phrases.push(token);
+                continue;
+            }
+            
+            match token.split_once(':') {
+                Some(("from", handle)) if !handle.is_empty() => search_query.from = Some(handle.trim_start_matches('@').to_string()),
+                Some(("since", date)) => search_query.since = Some(parse_date(date)?),
+                Some(("until", date)) => search_query.until = Some(parse_date(date)?),
+                // Posts cannot carry media yet; an empty result would read as "nothing matched".
+                Some(("has", "media")) => return Err(ApiError::new(ApiErrorCode::InvalidRequest, "has:media is not supported yet.")),
+                _ if token.len() > 1 && token.starts_with('#') => search_query.hashtags.push(token),
+                _ => {
+                    let term = strip_boolean_operators(&token);
+                    if !term.is_empty() { search_query.terms.push(term); }
+                }
+            }
+        }
+        
+        if search_query.is_empty() {
+            return Err(ApiError::new(ApiErrorCode::InvalidRequest, "Search query is empty."));
+        }
+        
+        Ok(search_query)
+    }
+    
+    pub fn full_text_expression(&self) -> Option<String> {
+        let required_terms = self.terms.iter()
+            .map(|t| format!("+{}", t))
+            .chain(self.phrases.iter().map(|p| format!("+\"{}\"", p)))
+            .collect::<Vec<String>>();
+        
+        (!required_terms.is_empty()).then(|| required_terms.join(" "))
+    }
+    
+    fn is_empty(&self) -> bool {
+        self.terms.is_empty() && self.phrases.is_empty() && self.hashtags.is_empty()
+            && self.from.is_none() && self.since.is_none() && self.until.is_none()
+    }
+}
+
+fn tokenize(query: &str) -> Vec<(String, bool)> {
+    query
+        .split('"')
+        .enumerate()
+        .flat_map(|(i, part)| {
+            let is_quoted = i % 2 == 1;
+            
+            if is_quoted {
+                let phrase = part.trim();
+                return if phrase.is_empty() { vec![] } else { vec![(phrase.to_string(), true)] };
+            }
+            
+            part.split_whitespace().map(|w| (w.to_string(), false)).collect()
+        })
+        .collect()
+}
+
+fn strip_boolean_operators(term: &str) -> String {
+    term.chars().filter(|c| !matches!(c, '+' | '-' | '<' | '>' | '(' | ')' | '~' | '*' | '@')).collect()
+}
+
+fn parse_date(date: &str) -> Result<chrono::NaiveDate, ApiError> {
+    chrono::NaiveDate::parse_from_str(date, SEARCH_DATE_FORMAT)
+        .map_err(|_| ApiError::new(ApiErrorCode::InvalidRequest, "Invalid date. Use YYYY-MM-DD."))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn parse_ok(query: &str) -> PostSearchQuery {
+        PostSearchQuery::parse(query).ok().expect("query should parse")
+    }
+    
+    #[test]
+    fn parse_reads_from_operator() {
+        assert_eq!(parse_ok("from:@NoixChou").from.as_deref(), Some("NoixChou"));
+        assert_eq!(parse_ok("from:NoixChou rust").from.as_deref(), Some("NoixChou"));
+    }
+    
+    #[test]
+    fn parse_reads_date_bounds() {
+        let query = parse_ok("since:2022-01-01 until:2022-01-31");
+        
+        assert_eq!(query.since, chrono::NaiveDate::from_ymd_opt(2022, 1, 1));
+        assert_eq!(query.until, chrono::NaiveDate::from_ymd_opt(2022, 1, 31));
+    }
+    
+    #[test]
+    fn parse_accepts_last_valid_date() {
+        let query = parse_ok("until:+262143-12-31");
+        
+        assert_eq!(query.until, Some(chrono::naive::MAX_DATE));
+        assert_eq!(query.until.and_then(|d| d.succ_opt()), None);
+    }
+    
+    #[test]
+    fn parse_rejects_invalid_dates() {
+        assert!(PostSearchQuery::parse("since:2022-13-01").is_err());
+        assert!(PostSearchQuery::parse("until:yesterday").is_err());
+    }
+    
+    #[test]
+    fn parse_keeps_quoted_phrases_together() {
+        let query = parse_ok("rust \"async await\" actix");
+        
+        assert_eq!(query.phrases, vec!["async await"]);
+        assert_eq!(query.terms, vec!["rust", "actix"]);
+        assert_eq!(query.full_text_expression().as_deref(), Some("+rust +actix +\"async await\""));
+    }
+    
+    #[test]
+    fn parse_collects_hashtags() {
+        let query = parse_ok("#rust #actix web");
+        
+        assert_eq!(query.hashtags, vec!["#rust", "#actix"]);
+        assert_eq!(query.terms, vec!["web"]);
+    }
+    
+    #[test]
+    fn parse_strips_boolean_operators() {
+        assert_eq!(parse_ok("-rust* +(web)").terms, vec!["rust", "web"]);
+    }
+    
+    #[test]
+    fn parse_rejects_queries_of_only_operators() {
+        assert!(PostSearchQuery::parse("+- <> ~*").is_err());
+        assert!(PostSearchQuery::parse("  \"\" ").is_err());
+    }
+    
+    #[test]
+    fn parse_rejects_has_media() {
+        let error = PostSearchQuery::parse("has:media rust").err().expect("has:media should be rejected");
+        
+        assert!(matches!(error.code, ApiErrorCode::InvalidRequest));
+        assert_eq!(error.message, "has:media is not supported yet.");
+    }
+}
\ No newline at end of file
@@ -0,0 +1,21 @@
+/// Decides who may create an account on this instance.
+#[derive(Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RegistrationMode {
+    Open,
+    InviteOnly,
+    ApprovalRequired,
+    Closed,
+}
+
+impl RegistrationMode {
+    pub fn from_env() -> Self {
+        match std::env::var("REGISTRATION_MODE").as_deref() {
+            Ok("open") | Err(_) => Self::Open,
+            Ok("invite_only") => Self::InviteOnly,
+            Ok("approval_required") => Self::ApprovalRequired,
+            Ok("closed") => Self::Closed,
+            Ok(_) => panic!("invalid REGISTRATION_MODE"),
+        }
+    }
+}
\ No newline at end of file
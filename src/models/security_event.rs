@@ -0,0 +1,130 @@
+use diesel::prelude::*;
+use once_cell::sync::OnceCell;
+
+use crate::DBConPool;
+use crate::models::get_now_date_time;
+use crate::models::user_session::SessionMetadata;
+use crate::schema::security_events;
+
+const SECURITY_EVENT_LIST_LIMIT_COUNT: i64 = 50;
+const DEFAULT_RETENTION_DAYS: i64 = 90;
+
+static RETENTION_DAYS: OnceCell<i64> = OnceCell::new();
+
+/// Checked at startup, since a bad value would otherwise stop the purge task and a negative one would empty the log.
+pub fn init_retention_days() {
+    let retention_days = match std::env::var("SECURITY_EVENT_RETENTION_DAYS") {
+        Ok(v) => v.parse::<i64>().ok().filter(|d| *d >= 1).expect("invalid SECURITY_EVENT_RETENTION_DAYS"),
+        Err(_) => DEFAULT_RETENTION_DAYS,
+    };
+    
+    RETENTION_DAYS.set(retention_days).expect("SECURITY_EVENT_RETENTION_DAYS is already initialized");
+}
+
+#[derive(Clone, Copy)]
+pub enum SecurityEventType {
+    LoginSucceeded,
+    LoginFailed,
+    Logout,
+    SessionRevoked,
+    TokenIssued,
+    TokenRevoked,
+    PasswordChanged,
+    PasswordReset,
+    EmailChangeRequested,
+    EmailChanged,
+    TwoFactorEnabled,
+    TwoFactorDisabled,
+    RecoveryCodesRegenerated,
+}
+
+impl SecurityEventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SecurityEventType::LoginSucceeded => "login_succeeded",
+            SecurityEventType::LoginFailed => "login_failed",
+            SecurityEventType::Logout => "logout",
+            SecurityEventType::SessionRevoked => "session_revoked",
+            SecurityEventType::TokenIssued => "token_issued",
+            SecurityEventType::TokenRevoked => "token_revoked",
+            SecurityEventType::PasswordChanged => "password_changed",
+            SecurityEventType::PasswordReset => "password_reset",
+            SecurityEventType::EmailChangeRequested => "email_change_requested",
+            SecurityEventType::EmailChanged => "email_changed",
+            SecurityEventType::TwoFactorEnabled => "two_factor_enabled",
+            SecurityEventType::TwoFactorDisabled => "two_factor_disabled",
+            SecurityEventType::RecoveryCodesRegenerated => "recovery_codes_regenerated",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SecurityEventIdPagination {
+    pub oldest_event_id: Option<i64>,
+}
+
+#[derive(Insertable)]
+#[table_name = "security_events"]
+pub struct InsertableSecurityEvent {
+    user_id: String,
+    event_type: &'static str,
+    detail: Option<String>,
+    ip_address: Option<String>,
+    user_agent: Option<String>,
+}
+
+#[derive(Serialize, Queryable)]
+pub struct SecurityEvent {
+    pub id: i64,
+    #[serde(skip)]
+    pub user_id: String,
+    pub event_type: String,
+    pub detail: Option<String>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    #[serde(serialize_with = "crate::models::serialize_naive_dt")]
+    pub created_at: chrono::NaiveDateTime,
+}
+
+impl SecurityEvent {
+    pub fn record(user_id: &String, event_type: SecurityEventType, detail: Option<String>, metadata: &SessionMetadata, db: &DBConPool) -> QueryResult<usize> {
+        use crate::schema::security_events::dsl;
+        
+        diesel::insert_into(dsl::security_events)
+            .values(&InsertableSecurityEvent {
+                user_id: user_id.clone(),
+                event_type: event_type.as_str(),
+                detail: detail.map(|d| d.chars().take(255).collect()),
+                ip_address: metadata.ip_address.clone(),
+                user_agent: metadata.user_agent.as_ref().map(|a| a.chars().take(255).collect()),
+            })
+            .execute(&crate::get_db_connection(db))
+    }
+    
+    /// Returns the newest events, or those older than `oldest_event_id` to page back through the log.
+    pub fn fetch_list_by_user(user_id: &String, oldest_event_id: Option<i64>, db: &DBConPool) -> QueryResult<Vec<Self>> {
+        use crate::schema::security_events::dsl;
+        
+        let mut query = dsl::security_events
+            .into_boxed()
+            .filter(dsl::user_id.eq(user_id));
+        
+        if let Some(id) = oldest_event_id {
+            query = query.filter(dsl::id.lt(id));
+        }
+        
+        query
+            .order(dsl::id.desc())
+            .limit(SECURITY_EVENT_LIST_LIMIT_COUNT)
+            .load::<Self>(&crate::get_db_connection(db))
+    }
+    
+    pub fn purge_expired(db: &DBConPool) -> QueryResult<usize> {
+        use crate::schema::security_events::dsl;
+        
+        let retention_days = *RETENTION_DAYS.get().expect("SECURITY_EVENT_RETENTION_DAYS is not initialized");
+        
+        diesel::delete(dsl::security_events.filter(dsl::created_at.lt((get_now_date_time() - chrono::Duration::days(retention_days)).naive_local())))
+            .execute(&crate::get_db_connection(db))
+    }
+}
\ No newline at end of file
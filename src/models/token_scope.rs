@@ -0,0 +1,34 @@
+#[derive(Clone, Copy, PartialEq)]
+pub enum TokenScope {
+    Read,
+    WritePosts,
+    WriteProfile,
+    Account,
+}
+
+// `Account` covers credentials, sessions and tokens, so it is only held by login sessions.
+pub const GRANTABLE_SCOPES: [TokenScope; 3] = [TokenScope::Read, TokenScope::WritePosts, TokenScope::WriteProfile];
+pub const SESSION_SCOPES: [TokenScope; 4] = [TokenScope::Read, TokenScope::WritePosts, TokenScope::WriteProfile, TokenScope::Account];
+
+impl TokenScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenScope::Read => "read",
+            TokenScope::WritePosts => "write:posts",
+            TokenScope::WriteProfile => "write:profile",
+            TokenScope::Account => "account",
+        }
+    }
+    
+    pub fn parse_grantable(scope: &str) -> Option<Self> {
+        GRANTABLE_SCOPES.iter().find(|s| s.as_str() == scope).copied()
+    }
+}
+
+pub fn parse_scope_list(scopes: &str) -> Vec<TokenScope> {
+    scopes.split_whitespace().filter_map(TokenScope::parse_grantable).collect()
+}
+
+pub fn join_scope_list(scopes: &[TokenScope]) -> String {
+    scopes.iter().map(|s| s.as_str()).collect::<Vec<&str>>().join(" ")
+}
\ No newline at end of file
@@ -0,0 +1,100 @@
+use hmac::{Hmac, Mac, NewMac};
+use rand::RngCore;
+use sha1::Sha1;
+
+const SECRET_BYTES: usize = 20;
+const STEP_SECONDS: i64 = 30;
+const CODE_DIGITS: u32 = 6;
+// Codes of the neighbouring steps are accepted as well, to allow for clock drift on the device.
+const ALLOWED_DRIFT_STEPS: i64 = 1;
+const SECRET_ALPHABET: base32::Alphabet = base32::Alphabet::RFC4648 { padding: false };
+
+pub fn generate_secret() -> String {
+    let mut secret = [0u8; SECRET_BYTES];
+    rand::thread_rng().fill_bytes(&mut secret);
+    
+    base32::encode(SECRET_ALPHABET, &secret)
+}
+
+pub fn provisioning_uri(secret: &str, account_name: &str) -> String {
+    let issuer = std::env::var("TOTP_ISSUER").unwrap_or_else(|_| "rust-sns".to_string());
+    
+    let mut uri = url::Url::parse("otpauth://totp").expect("invalid otpauth URI");
+    uri.set_path(&format!("/{}:{}", issuer, account_name));
+    uri.query_pairs_mut()
+        .append_pair("secret", secret)
+        .append_pair("issuer", &issuer)
+        .append_pair("algorithm", "SHA1")
+        .append_pair("digits", &CODE_DIGITS.to_string())
+        .append_pair("period", &STEP_SECONDS.to_string());
+    
+    uri.to_string()
+}
+
+/// Returns the time step the code belongs to, so that callers can refuse a code that was used before.
+pub fn verify(secret: &str, code: &str, unix_time: i64) -> Option<i64> {
+    let secret = base32::decode(SECRET_ALPHABET, secret)?;
+    let code = code.trim();
+    
+    if code.len() != CODE_DIGITS as usize || !code.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    
+    let current_step = unix_time / STEP_SECONDS;
+    
+    (current_step - ALLOWED_DRIFT_STEPS..=current_step + ALLOWED_DRIFT_STEPS)
+        .find(|step| format!("{:0width$}", code_at(&secret, *step), width = CODE_DIGITS as usize) == code)
+}
+
+fn code_at(secret: &[u8], step: i64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(&(step as u64).to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+    
+    // Dynamic truncation of RFC 4226.
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([digest[offset], digest[offset + 1], digest[offset + 2], digest[offset + 3]]) & 0x7fff_ffff;
+    
+    binary % 10u32.pow(CODE_DIGITS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    // The SHA-1 vectors of RFC 6238 Appendix B, cut down to six digits.
+    const RFC6238_SECRET: &[u8] = b"12345678901234567890";
+    const RFC6238_VECTORS: [(i64, u32); 6] = [
+        (59, 287082),
+        (1111111109, 81804),
+        (1111111111, 50471),
+        (1234567890, 5924),
+        (2000000000, 279037),
+        (20000000000, 353130),
+    ];
+    
+    #[test]
+    fn code_at_matches_rfc6238_vectors() {
+        for (unix_time, code) in RFC6238_VECTORS {
+            assert_eq!(code_at(RFC6238_SECRET, unix_time / STEP_SECONDS), code, "at {}", unix_time);
+        }
+    }
+    
+    #[test]
+    fn verify_accepts_codes_within_drift() {
+        let secret = base32::encode(SECRET_ALPHABET, RFC6238_SECRET);
+        
+        assert_eq!(verify(&secret, "005924", 1234567890), Some(1234567890 / STEP_SECONDS));
+        assert_eq!(verify(&secret, "005924", 1234567890 + STEP_SECONDS), Some(1234567890 / STEP_SECONDS));
+        assert_eq!(verify(&secret, "005924", 1234567890 + STEP_SECONDS * 2), None);
+    }
+    
+    #[test]
+    fn verify_rejects_malformed_codes() {
+        let secret = base32::encode(SECRET_ALPHABET, RFC6238_SECRET);
+        
+        assert_eq!(verify(&secret, "5924", 1234567890), None);
+        assert_eq!(verify(&secret, "00592a", 1234567890), None);
+        assert_eq!(verify("not base32!", "005924", 1234567890), None);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,118 @@
+use diesel::prelude::*;
+
+use crate::DBConPool;
+use crate::models::{get_now_date_time, get_now_naive_date_time};
+use crate::models::error::{ApiError, ApiErrorCode};
+use crate::models::user_credential::UserCredential;
+use crate::models::user_token::{generate_token, hash_token};
+use crate::schema::two_factor_challenges;
+
+const CHALLENGE_TOKEN_PREFIX: &str = "rsns_tf_";
+const CHALLENGE_VALIDITY_MINUTES: i64 = 5;
+const MAX_FAILED_ATTEMPTS: i32 = 5;
+
+#[derive(Deserialize)]
+pub struct InputTwoFactorLogin {
+    pub challenge_token: String,
+    pub code: String,
+}
+
+#[derive(Insertable)]
+#[table_name = "two_factor_challenges"]
+pub struct InsertableTwoFactorChallenge {
+    token: String,
+    user_id: String,
+    device_name: Option<String>,
+    expired_at: chrono::NaiveDateTime,
+}
+
+#[derive(Queryable)]
+pub struct TwoFactorChallenge {
+    pub token: String,
+    pub user_id: String,
+    pub device_name: Option<String>,
+}
+
+pub enum TwoFactorVerificationError {
+    /// The challenge was genuine but the code was wrong, so the attempt belongs to the challenge's account.
+    InvalidCode { user_id: String },
+    Rejected(ApiError),
+}
+
+#[derive(Serialize)]
+pub struct IssuedTwoFactorChallenge {
+    challenge_token: String,
+    #[serde(serialize_with = "crate::models::serialize_naive_dt")]
+    expired_at: chrono::NaiveDateTime,
+}
+
+impl TwoFactorChallenge {
+    /// Issued in place of a `UserToken` once the password matched, and exchanged for one with the second factor.
+    pub fn issue(user_id: &String, device_name: Option<String>, db: &DBConPool) -> QueryResult<IssuedTwoFactorChallenge> {
+        use crate::schema::two_factor_challenges::dsl;
+        
+        let raw_token = generate_token(CHALLENGE_TOKEN_PREFIX);
+        let expired_at = (get_now_date_time() + chrono::Duration::minutes(CHALLENGE_VALIDITY_MINUTES)).naive_local();
+        
+        diesel::insert_into(dsl::two_factor_challenges)
+            .values(&InsertableTwoFactorChallenge {
+                token: hash_token(&raw_token),
+                user_id: user_id.clone(),
+                device_name,
+                expired_at,
+            })
+            .execute(&crate::get_db_connection(db))
+            .map(|_| IssuedTwoFactorChallenge { challenge_token: raw_token, expired_at })
+    }
+    
+    /// Returns the credential and the device name given at login once the code matches.
+    pub fn verify(input: &InputTwoFactorLogin, db: &DBConPool) -> Result<(UserCredential, Option<String>), TwoFactorVerificationError> {
+        use crate::schema::two_factor_challenges::dsl;
+        
+        let conn = crate::get_db_connection(db);
+        
+        let challenge = filter_for_active_tokens!(dsl::two_factor_challenges)
+            .select((dsl::token, dsl::user_id, dsl::device_name))
+            .filter(dsl::token.eq(hash_token(&input.challenge_token)))
+            .first::<Self>(&conn)
+            .map_err(|_| invalid_challenge_error())?;
+        
+        let credential = UserCredential::fetch_by_id(&challenge.user_id, db)
+            .map_err(|_| invalid_challenge_error())?;
+        
+        let result = conn.transaction::<_, diesel::result::Error, _>(|| {
+            if !credential.verify_second_factor(&input.code, &conn)? {
+                return Ok(false);
+            }
+            
+            diesel::update(dsl::two_factor_challenges.filter(dsl::token.eq(&challenge.token)).filter(dsl::deleted_at.is_null()))
+                .set(dsl::deleted_at.eq(get_now_naive_date_time()))
+                .execute(&conn)
+                .map(|count| count > 0)
+        });
+        
+        match result {
+            Ok(true) => Ok((credential, challenge.device_name)),
+            Ok(false) => {
+                // A new challenge needs the password again, so this bounds the guesses per password check.
+                let challenge_filter = dsl::two_factor_challenges.filter(dsl::token.eq(&challenge.token));
+                
+                diesel::update(challenge_filter)
+                    .set(dsl::failed_attempts.eq(dsl::failed_attempts + 1))
+                    .execute(&conn)
+                    .and_then(|_| diesel::update(challenge_filter.filter(dsl::failed_attempts.ge(MAX_FAILED_ATTEMPTS)))
+                        .set(dsl::deleted_at.eq(get_now_naive_date_time()))
+                        .execute(&conn)
+                    )
+                    .map_err(|_| TwoFactorVerificationError::Rejected(ApiError::new(ApiErrorCode::ServerError, "Failed to verify code.")))?;
+                
+                Err(TwoFactorVerificationError::InvalidCode { user_id: challenge.user_id })
+            }
+            Err(_) => Err(TwoFactorVerificationError::Rejected(ApiError::new(ApiErrorCode::ServerError, "Failed to verify code."))),
+        }
+    }
+}
+
+fn invalid_challenge_error() -> TwoFactorVerificationError {
+    TwoFactorVerificationError::Rejected(ApiError::new(ApiErrorCode::InvalidToken, "Challenge token is invalid or expired."))
+}
\ No newline at end of file
@@ -1,13 +1,19 @@
+use actix_web::http::header::EntityTag;
 use diesel::prelude::*;
+use diesel::result::{DatabaseErrorKind, Error};
 use log::error;
 use serde::Serialize;
 use validator::{Validate, ValidationError, ValidationErrors};
 
 use crate::DBConPool;
 use crate::models::error::{ApiError, ApiErrorCode};
-use crate::models::get_now_naive_date_time;
+use crate::models::{escape_like_pattern, get_now_date_time, get_now_naive_date_time};
+use crate::models::user_handle_history::UserHandleHistory;
 use crate::schema::users;
 
+const USER_SEARCH_LIMIT_COUNT: i64 = 20;
+const HANDLE_CHANGE_INTERVAL_DAYS: i64 = 7;
+
 #[derive(Deserialize, Validate)]
 pub struct InputUser {
     #[validate(length(min = 3, max = 20))]
@@ -23,18 +29,77 @@ pub struct InputUser {
     pub is_private: bool,
 }
 
-#[derive(AsChangeset, Deserialize, Validate)]
-#[table_name = "users"]
+#[derive(Deserialize, Validate)]
 pub struct InputPatchUser {
+    #[serde(default, deserialize_with = "crate::models::deserialize_patch_field")]
     #[validate(length(min = 1, max = 100))]
-    pub display_name: Option<String>,
+    pub display_name: Option<Option<String>>,
+    #[serde(default, deserialize_with = "crate::models::deserialize_patch_field")]
     #[validate(length(max = 300))]
-    pub description: Option<String>,
+    pub description: Option<Option<String>>,
+    #[serde(default, deserialize_with = "crate::models::deserialize_patch_field")]
     #[validate(custom = "validate_birthday")]
-    pub birthday: Option<chrono::NaiveDate>,
+    pub birthday: Option<Option<chrono::NaiveDate>>,
+    #[serde(default, deserialize_with = "crate::models::deserialize_patch_field")]
     #[validate(custom = "validate_website", length(max = 100))]
-    pub website: String,
-    pub is_private: bool,
+    pub website: Option<Option<String>>,
+    #[serde(default, deserialize_with = "crate::models::deserialize_patch_field")]
+    pub is_private: Option<Option<bool>>,
+}
+
+#[derive(AsChangeset)]
+#[table_name = "users"]
+pub struct UserChangeset {
+    display_name: Option<String>,
+    description: Option<String>,
+    birthday: Option<Option<chrono::NaiveDate>>,
+    website: Option<String>,
+    is_private: Option<bool>,
+}
+
+impl UserChangeset {
+    pub fn new(patch_user: InputPatchUser) -> Result<Self, ValidationErrors> {
+        let mut errors = patch_user.validate().err().unwrap_or_else(ValidationErrors::new);
+        
+        let changeset = Self {
+            display_name: reject_null("display_name", patch_user.display_name, &mut errors),
+            description: reject_null("description", patch_user.description, &mut errors),
+            birthday: patch_user.birthday,
+            website: reject_null("website", patch_user.website, &mut errors),
+            is_private: reject_null("is_private", patch_user.is_private, &mut errors),
+        };
+        
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        
+        Ok(changeset)
+    }
+    
+    fn has_changes(&self) -> bool {
+        self.display_name.is_some() || self.description.is_some() || self.birthday.is_some()
+            || self.website.is_some() || self.is_private.is_some()
+    }
+}
+
+fn reject_null<T>(field: &'static str, value: Option<Option<T>>, errors: &mut ValidationErrors) -> Option<T> {
+    match value {
+        Some(None) => {
+            errors.add(field, ValidationError::new("null"));
+            None
+        }
+        v => v.flatten()
+    }
+}
+
+#[derive(Deserialize, Validate)]
+pub struct InputUserIdName {
+    #[validate(length(min = 3, max = 20))]
+    pub id_name: String,
+}
+
+fn id_name_taken_error() -> ApiError {
+    ApiError::new(ApiErrorCode::InvalidRequest, "ID name is already taken.")
 }
 
 fn validate_birthday(birthday: &chrono::NaiveDate) -> Result<(), ValidationError> {
@@ -84,9 +149,22 @@ impl InsertableUser {
     }
 }
 
+pub enum UserUpdateError {
+    InvalidParameter(ValidationErrors),
+    PreconditionFailed,
+    NotFound,
+    Failed,
+}
+
 #[derive(Serialize)]
 pub struct FilteredUser(User);
 
+impl FilteredUser {
+    pub fn entity_tag(&self) -> EntityTag {
+        self.0.entity_tag()
+    }
+}
+
 #[derive(Serialize)]
 #[serde(rename_all = "snake_case")]
 pub enum UserTagged {
@@ -108,6 +186,18 @@ pub struct User {
     pub updated_at: chrono::NaiveDateTime,
     #[serde(skip)]
     pub deleted_at: Option<chrono::NaiveDateTime>,
+    #[serde(skip)]
+    pub deactivated_at: Option<chrono::NaiveDateTime>,
+    #[serde(skip)]
+    pub version: i32,
+}
+
+macro_rules! filter_for_searchable_users {
+    ($query:expr) => {
+        $query
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::deactivated_at.is_null())
+    }
 }
 
 impl User {
@@ -115,6 +205,15 @@ impl User {
         UserTagged::User(self.filter_for_response())
     }
     
+    pub fn entity_tag(&self) -> EntityTag {
+        // `updated_at` only has second precision, so two changes within a second would share a tag.
+        EntityTag::strong(format!("{}-{}", self.id, self.version))
+    }
+    
+    pub fn is_deactivated(&self) -> bool {
+        self.deactivated_at.is_some()
+    }
+    
     pub fn filter_for_response(&self) -> FilteredUser {
         let mut user = self.clone();
         if !user.is_private { return FilteredUser(user); }
@@ -136,24 +235,55 @@ impl User {
         response_item_insertion_result!(modified_rows_count, insertable_user.id)
     }
     
-    pub fn update(user: InputPatchUser, user_id: &String, db: &DBConPool) -> Result<User, ApiError> {
+    /// With `expected_version`, the update only applies while the row is still at that version,
+    /// so a concurrent change in between makes it fail instead of being overwritten.
+    pub fn update(user: InputPatchUser, user_id: &String, expected_version: Option<i32>, db: &DBConPool) -> Result<User, UserUpdateError> {
         use crate::schema::users::dsl;
         
-        if let Err(_) = user.validate() {
-            return Err(ApiError::new(ApiErrorCode::InvalidRequest, "Invalid parameter."));
+        let changeset = UserChangeset::new(user).map_err(UserUpdateError::InvalidParameter)?;
+        
+        if !changeset.has_changes() {
+            return Self::fetch_by_id(user_id, db).map_err(|_| UserUpdateError::NotFound);
+        }
+        
+        let conn = crate::get_db_connection(db);
+        let target = dsl::users
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::id.eq(user_id));
+        let changes = (&changeset, dsl::version.eq(dsl::version + 1));
+        
+        let result = match expected_version {
+            Some(v) => diesel::update(target.filter(dsl::version.eq(v))).set(changes).execute(&conn),
+            None => diesel::update(target).set(changes).execute(&conn),
+        };
+        
+        match result {
+            Ok(0) if expected_version.is_some() => Err(UserUpdateError::PreconditionFailed),
+            Ok(_) => Self::fetch_by_id(user_id, db).map_err(|_| UserUpdateError::NotFound),
+            Err(e) => {
+                error!("query was failed: {:?}", e);
+                Err(UserUpdateError::Failed)
+            }
         }
+    }
+    
+    pub fn deactivate(&self, db: &DBConPool) -> QueryResult<usize> {
+        use crate::schema::users::dsl;
+        
+        diesel::update(dsl::users.filter(dsl::id.eq(&self.id)))
+            .set(dsl::deactivated_at.eq(get_now_naive_date_time()))
+            .execute(&crate::get_db_connection(db))
+    }
     
+    pub fn reactivate(user_id: &String, db: &DBConPool) -> QueryResult<usize> {
+        use crate::schema::users::dsl;
+        
         diesel::update(dsl::users
-            .filter(dsl::deleted_at.is_null())
             .filter(dsl::id.eq(user_id))
+            .filter(dsl::deactivated_at.is_not_null())
         )
-            .set(&user)
+            .set(dsl::deactivated_at.eq(None::<chrono::NaiveDateTime>))
             .execute(&crate::get_db_connection(db))
-            .map(|_| {
-                Self::fetch_by_id(user_id, db)
-                    .unwrap_or_else(|e| panic!("User updated but failed to fetch ({}): {}", user_id, e))
-            })
-            .map_err(|_| ApiError::new(ApiErrorCode::NotFound, "User does not exist."))
     }
     
     pub fn fetch_by_id(user_id: &String, db: &DBConPool) -> QueryResult<User> {
@@ -164,5 +294,147 @@ impl User {
             .filter(dsl::id.eq(user_id))
             .first::<User>(&crate::get_db_connection(db))
     }
+    
+    pub fn fetch_by_id_name(id_name: &String, db: &DBConPool) -> QueryResult<User> {
+        use crate::schema::users::dsl;
+        
+        let conn = crate::get_db_connection(db);
+        let id_name = id_name.trim_start_matches('@');
+        
+        // `users.id_name` uses the table's case-insensitive collation, so a plain `=` matches
+        // handles regardless of case while still hitting the unique index.
+        dsl::users
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::id_name.eq(id_name))
+            .first::<User>(&conn)
+            .or_else(|e| match e {
+                // A handle that was changed recently keeps resolving to its previous owner while reserved.
+                diesel::NotFound => {
+                    let user_id = UserHandleHistory::fetch_reserving_user_id(id_name, &conn)?;
+                    
+                    dsl::users
+                        .filter(dsl::deleted_at.is_null())
+                        .filter(dsl::id.eq(user_id))
+                        .first::<User>(&conn)
+                }
+                e => Err(e)
+            })
+    }
+    
+    pub fn change_id_name(new_id_name: InputUserIdName, user: &User, expected_version: Option<i32>, db: &DBConPool) -> Result<User, ApiError> {
+        use crate::schema::users::dsl;
+        
+        if let Err(_) = new_id_name.validate() {
+            return Err(ApiError::new(ApiErrorCode::InvalidRequest, "Invalid parameter."));
+        }
+        
+        let conn = crate::get_db_connection(db);
+        
+        // The checks run under row locks in the same transaction as the update, so that concurrent
+        // renames of this user or onto the same handle are serialized instead of racing past them.
+        let result = conn.transaction::<_, Error, _>(|| {
+            let current = dsl::users
+                .filter(dsl::deleted_at.is_null())
+                .filter(dsl::id.eq(&user.id))
+                .for_update()
+                .first::<User>(&conn)?;
+            
+            if expected_version.map_or(false, |v| v != current.version) {
+                return Ok(Err(ApiError::new(ApiErrorCode::PreconditionFailed, "Resource was modified by another request.")));
+            }
+            
+            if let Ok(latest) = UserHandleHistory::fetch_latest_by_user(&user.id, &conn) {
+                if latest.created_at > (get_now_date_time() - chrono::Duration::days(HANDLE_CHANGE_INTERVAL_DAYS)).naive_local() {
+                    return Ok(Err(ApiError::new(ApiErrorCode::RateLimited, "ID name was changed recently.")));
+                }
+            }
+            
+            let is_taken = dsl::users
+                .filter(dsl::id_name.eq(&new_id_name.id_name))
+                .filter(dsl::id.ne(&user.id))
+                .for_update()
+                .first::<User>(&conn)
+                .optional()?
+                .is_some();
+            let is_reserved = UserHandleHistory::lock_reserving_user_id(&new_id_name.id_name, &conn)?
+                .map_or(false, |owner_id| owner_id != user.id);
+            
+            if is_taken || is_reserved {
+                return Ok(Err(id_name_taken_error()));
+            }
+            
+            UserHandleHistory::record(&user.id, &current.id_name, &conn)?;
+            
+            diesel::update(dsl::users.filter(dsl::id.eq(&user.id)))
+                .set((dsl::id_name.eq(&new_id_name.id_name), dsl::version.eq(dsl::version + 1)))
+                .execute(&conn)
+                .map(|_| Ok(()))
+        });
+        
+        match result {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => return Err(e),
+            Err(Error::NotFound) => return Err(ApiError::new(ApiErrorCode::NotFound, "User does not exist.")),
+            // The unique index is the last line of defence, e.g. against a new user created with the handle meanwhile.
+            Err(Error::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => return Err(id_name_taken_error()),
+            Err(e) => {
+                error!("failed to change id_name ({}): {:?}", user.id, e);
+                return Err(ApiError::new(ApiErrorCode::ServerError, "Failed to change ID name."));
+            }
+        }
+        
+        Self::fetch_by_id(&user.id, db)
+            .map_err(|_| ApiError::new(ApiErrorCode::NotFound, "User does not exist."))
+    }
+    
+    /// Prefix matches come first and can use the indexes on both columns; the subsequence match scans
+    /// the table, so it only runs to fill a page the prefix matches do not.
+    /// Follows and blocks do not exist yet, so `_viewer_id` is not used for ranking or filtering so far.
+    pub fn search(query: &String, page: i64, _viewer_id: Option<&String>, db: &DBConPool) -> QueryResult<Vec<User>> {
+        use diesel::dsl::not;
+        use crate::schema::users::dsl;
+        
+        let conn = crate::get_db_connection(db);
+        let offset = page * USER_SEARCH_LIMIT_COUNT;
+        
+        let escaped_query = escape_like_pattern(query.trim_start_matches('@'));
+        let prefix_pattern = format!("{}%", escaped_query);
+        // Matches the query as a subsequence, e.g. "nxc" matches "NoixChou".
+        let fuzzy_pattern = escaped_query.chars().fold(String::from("%"), |mut pattern, c| {
+            pattern.push(c);
+            if c != '\\' { pattern.push('%'); }
+            pattern
+        });
+        
+        let prefix_count = filter_for_searchable_users!(dsl::users)
+            .filter(dsl::id_name.like(&prefix_pattern).or(dsl::display_name.like(&prefix_pattern)))
+            .count()
+            .get_result::<i64>(&conn)?;
+        
+        let mut users = match offset < prefix_count {
+            true => filter_for_searchable_users!(dsl::users)
+                .filter(dsl::id_name.like(&prefix_pattern).or(dsl::display_name.like(&prefix_pattern)))
+                .order((dsl::id_name.like(&prefix_pattern).desc(), dsl::id_name.asc()))
+                .limit(USER_SEARCH_LIMIT_COUNT)
+                .offset(offset)
+                .load::<User>(&conn)?,
+            false => vec![],
+        };
+        
+        let remaining_count = USER_SEARCH_LIMIT_COUNT - users.len() as i64;
+        
+        if remaining_count > 0 {
+            let fuzzy_users = filter_for_searchable_users!(dsl::users)
+                .filter(dsl::id_name.like(&fuzzy_pattern).or(dsl::display_name.like(&fuzzy_pattern)))
+                .filter(not(dsl::id_name.like(&prefix_pattern).or(dsl::display_name.like(&prefix_pattern))))
+                .order(dsl::id_name.asc())
+                .limit(remaining_count)
+                .offset((offset - prefix_count).max(0))
+                .load::<User>(&conn)?;
+            
+            users.extend(fuzzy_users);
+        }
+        
+        Ok(users)
+    }
 }
-
@@ -2,11 +2,28 @@ use argon2::{Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVe
 use argon2::password_hash::{rand_core::OsRng, SaltString};
 use diesel::prelude::*;
 use log::error;
-use rand::Rng;
+use once_cell::sync::OnceCell;
 use validator::{Validate, ValidationErrors};
 
-use crate::DBConPool;
-use crate::schema::user_credentials;
+use crate::{DBConnection, DBConPool};
+use crate::models::{get_now_date_time, get_now_naive_date_time};
+use crate::models::error::{ApiError, ApiErrorCode};
+use crate::models::invite_code::InviteCode;
+use crate::models::magic_link_token::MagicLinkToken;
+use crate::models::oauth_app::OAuthApp;
+use crate::models::personal_access_token::PersonalAccessToken;
+use crate::models::registration_mode::RegistrationMode;
+use crate::models::totp;
+use crate::models::user_recovery_code::UserRecoveryCode;
+use crate::models::user_token::UserToken;
+use crate::schema::{email_change_tokens, email_verification_tokens, invite_codes, magic_link_tokens, oauth_authorization_codes, password_reset_tokens, personal_access_tokens, post_import_errors, post_imports, posts, security_events, two_factor_challenges, user_credentials, user_data_exports, user_handle_histories, user_images, user_recovery_codes, user_refresh_tokens, user_sessions, user_tokens, users};
+
+pub const ACCOUNT_DELETION_GRACE_DAYS: i64 = 30;
+const DEFAULT_ARGON2_MEMORY_KIB: u32 = 37888;
+const DEFAULT_ARGON2_ITERATIONS: u32 = 1;
+const DEFAULT_ARGON2_PARALLELISM: u32 = 1;
+
+static ARGON2_PARAMS: OnceCell<Params> = OnceCell::new();
 
 #[derive(Deserialize, Validate)]
 pub struct InputUserCredential {
@@ -17,12 +34,62 @@ pub struct InputUserCredential {
     pub email: String,
 }
 
+#[derive(Deserialize)]
+pub struct InputPassword {
+    pub password: String,
+}
+
+#[derive(Deserialize)]
+pub struct InputPasswordChange {
+    pub current_password: String,
+    #[serde(rename = "password")]
+    pub raw_password: String,
+}
+
+#[derive(Deserialize)]
+pub struct InputTwoFactorCode {
+    pub code: String,
+}
+
+#[derive(Deserialize)]
+pub struct InputPasswordAndCode {
+    pub password: String,
+    pub code: String,
+}
+
+#[derive(Serialize)]
+pub struct TwoFactorEnrollment {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Deserialize)]
+pub struct InputRegistration {
+    #[serde(flatten)]
+    pub credential: InputUserCredential,
+    pub invite_code: Option<String>,
+}
+
+pub struct Registration {
+    pub id: String,
+    pub is_pending_approval: bool,
+}
+
+pub enum RegistrationError {
+    InvalidParameter(ValidationErrors),
+    Denied(ApiError),
+    Failed,
+}
+
 #[derive(Insertable)]
 #[table_name = "user_credentials"]
 pub struct InsertableUserCredential {
     id: String,
     password_hash: String,
     email: String,
+    approved_at: Option<chrono::NaiveDateTime>,
+    invited_by: Option<String>,
+    invite_code_id: Option<String>,
 }
 
 impl InsertableUserCredential {
@@ -33,6 +100,9 @@ impl InsertableUserCredential {
             id: uuid::Uuid::new_v4().to_string(),
             password_hash: hash_password(&new_credential.raw_password),
             email: new_credential.email,
+            approved_at: Some(get_now_naive_date_time()),
+            invited_by: None,
+            invite_code_id: None,
         })
     }
 }
@@ -43,6 +113,21 @@ pub struct UserCredential {
     #[serde(skip)]
     pub password_hash: String,
     pub email: String,
+    #[serde(serialize_with = "crate::models::serialize_option_naive_dt")]
+    pub email_verified_at: Option<chrono::NaiveDateTime>,
+    #[serde(skip)]
+    pub totp_secret: Option<String>,
+    #[serde(serialize_with = "crate::models::serialize_option_naive_dt")]
+    pub totp_enabled_at: Option<chrono::NaiveDateTime>,
+    #[serde(skip)]
+    pub totp_last_used_step: Option<i64>,
+    pub magic_link_enabled: bool,
+    pub is_admin: bool,
+    #[serde(serialize_with = "crate::models::serialize_option_naive_dt")]
+    pub approved_at: Option<chrono::NaiveDateTime>,
+    pub invited_by: Option<String>,
+    #[serde(skip)]
+    pub invite_code_id: Option<String>,
     #[serde(serialize_with = "crate::models::serialize_naive_dt")]
     pub created_at: chrono::NaiveDateTime,
     #[serde(serialize_with = "crate::models::serialize_naive_dt")]
@@ -52,16 +137,68 @@ pub struct UserCredential {
 }
 
 impl UserCredential {
-    pub fn insert(user: InputUserCredential, db: &DBConPool) -> Result<Option<String>, ValidationErrors> {
+    /// Creates the account as far as `mode` allows. An invite code is optional outside the invite-only mode,
+    /// but a valid one is still recorded, and it lets the account skip the approval queue.
+    pub fn register(input: InputRegistration, mode: RegistrationMode, db: &DBConPool) -> Result<Registration, RegistrationError> {
         use crate::schema::user_credentials::dsl;
-    
-        let insertable_credential = InsertableUserCredential::new(user)?;
-    
-        let modified_rows_count = diesel::insert_into(dsl::user_credentials)
-            .values(&insertable_credential)
-            .execute(&crate::get_db_connection(db));
-    
-        response_item_insertion_result!(modified_rows_count, insertable_credential.id)
+        
+        match mode {
+            RegistrationMode::Closed => {
+                return Err(RegistrationError::Denied(ApiError::new(ApiErrorCode::RegistrationClosed, "Registration is closed.")));
+            }
+            RegistrationMode::InviteOnly if input.invite_code.is_none() => {
+                return Err(RegistrationError::Denied(ApiError::new(ApiErrorCode::InvalidRequest, "An invite code is required.")));
+            }
+            _ => {}
+        }
+        
+        let mut insertable_credential = InsertableUserCredential::new(input.credential)
+            .map_err(RegistrationError::InvalidParameter)?;
+        let conn = crate::get_db_connection(db);
+        
+        let result = conn.transaction::<_, diesel::result::Error, _>(|| {
+            let invite_code = match &input.invite_code {
+                Some(c) => match InviteCode::redeem(c, &conn)? {
+                    Some(c) => Some(c),
+                    None => return Ok(None)
+                },
+                None => None,
+            };
+            
+            // Anyone may invite, so only an administrator's invitation stands in for the approval.
+            let is_invited_by_admin = match &invite_code {
+                Some(c) => dsl::user_credentials
+                    .select(dsl::is_admin)
+                    .filter(dsl::id.eq(&c.inviter_id))
+                    .first::<bool>(&conn)?,
+                None => false,
+            };
+            
+            if mode == RegistrationMode::ApprovalRequired && !is_invited_by_admin {
+                insertable_credential.approved_at = None;
+            }
+            if let Some(c) = invite_code {
+                insertable_credential.invited_by = Some(c.inviter_id);
+                insertable_credential.invite_code_id = Some(c.id);
+            }
+            
+            diesel::insert_into(dsl::user_credentials)
+                .values(&insertable_credential)
+                .execute(&conn)
+                .map(|_| Some(()))
+        });
+        
+        match result {
+            Ok(Some(_)) => Ok(Registration {
+                id: insertable_credential.id,
+                is_pending_approval: insertable_credential.approved_at.is_none(),
+            }),
+            Ok(None) => Err(RegistrationError::Denied(ApiError::new(ApiErrorCode::InvalidRequest, "Invite code is invalid or expired."))),
+            Err(e) => {
+                error!("query was failed: {:?}", e);
+                Err(RegistrationError::Failed)
+            }
+        }
     }
     
     pub fn fetch_by_id(user_id: &String, db: &DBConPool) -> QueryResult<Self> {
@@ -82,31 +219,513 @@ impl UserCredential {
             .first::<Self>(&crate::get_db_connection(db))
     }
     
+    pub fn fetch_deleted_by_email(email: &String, db: &DBConPool) -> QueryResult<Self> {
+        use crate::schema::user_credentials::dsl;
+        
+        dsl::user_credentials
+            .filter(dsl::deleted_at.gt(deletion_grace_period_start()))
+            .filter(dsl::email.eq(email))
+            .first::<Self>(&crate::get_db_connection(db))
+    }
+    
     pub fn verify_with_input(input_credential: &InputUserCredential, db: &DBConPool) -> Result<Self, ()> {
-        let stored_credential = Self::fetch_by_email(&input_credential.email, db)
-            .map(|c| {
-                build_argon2().verify_password(
-                    input_credential.raw_password.as_bytes(),
-                    &PasswordHash::new(&c.password_hash)?,
-                ).map(|_| c)
-            });
+        Self::verify_stored_credential(input_credential, Self::fetch_by_email(&input_credential.email, db), db)
+    }
+    
+    pub fn verify_deleted_with_input(input_credential: &InputUserCredential, db: &DBConPool) -> Result<Self, ()> {
+        Self::verify_stored_credential(input_credential, Self::fetch_deleted_by_email(&input_credential.email, db), db)
+    }
+    
+    pub fn is_email_verified(&self) -> bool {
+        self.email_verified_at.is_some()
+    }
+    
+    pub fn is_approved(&self) -> bool {
+        self.approved_at.is_some()
+    }
+    
+    pub fn fetch_pending_approval_list(db: &DBConPool) -> QueryResult<Vec<Self>> {
+        use crate::schema::user_credentials::dsl;
+        
+        dsl::user_credentials
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::approved_at.is_null())
+            .order(dsl::created_at.asc())
+            .load::<Self>(&crate::get_db_connection(db))
+    }
+    
+    pub fn fetch_pending_approval_by_id(user_id: &String, db: &DBConPool) -> QueryResult<Self> {
+        use crate::schema::user_credentials::dsl;
+        
+        dsl::user_credentials
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::approved_at.is_null())
+            .filter(dsl::id.eq(user_id))
+            .first::<Self>(&crate::get_db_connection(db))
+    }
+    
+    pub fn approve(&self, db: &DBConPool) -> QueryResult<usize> {
+        use crate::schema::user_credentials::dsl;
+        
+        diesel::update(dsl::user_credentials.filter(dsl::id.eq(&self.id)).filter(dsl::approved_at.is_null()))
+            .set(dsl::approved_at.eq(get_now_naive_date_time()))
+            .execute(&crate::get_db_connection(db))
+    }
+    
+    /// A rejected account never got in, so nothing is kept for a grace period.
+    pub fn reject(&self, db: &DBConPool) -> QueryResult<usize> {
+        use crate::schema::user_credentials::dsl;
+        
+        let conn = crate::get_db_connection(db);
+        
+        conn.transaction(|| {
+            let pending_id = dsl::user_credentials
+                .select(dsl::id)
+                .filter(dsl::id.eq(&self.id))
+                .filter(dsl::approved_at.is_null())
+                .for_update()
+                .first::<String>(&conn)
+                .optional()?;
+            
+            match pending_id {
+                Some(id) => Self::purge_account(&id, &conn),
+                None => Ok(0),
+            }
+        })
+    }
+    
+    pub fn verify_password(&self, raw_password: &String) -> bool {
+        PasswordHash::new(&self.password_hash)
+            .and_then(|h| build_argon2().verify_password(raw_password.as_bytes(), &h))
+            .is_ok()
+    }
+    
+    pub fn change_password(&self, input: InputPasswordChange, current_token: &String, db: &DBConPool) -> Result<(), ApiError> {
+        if !self.verify_password(&input.current_password) {
+            return Err(ApiError::new(ApiErrorCode::AuthFailed, "Invalid credentials."));
+        }
+        
+        let new_credential = InputUserCredential { raw_password: input.raw_password, email: self.email.clone() };
+        
+        if let Err(_) = new_credential.validate() {
+            return Err(ApiError::new(ApiErrorCode::InvalidRequest, "Invalid parameter."));
+        }
+        
+        let conn = crate::get_db_connection(db);
+        let current_family_id = UserToken::fetch_family_id(current_token, &conn)?;
+        
+        // Whoever knew the old password may have authorized apps or created tokens with it,
+        // so only the session that made the change survives.
+        conn.transaction::<_, diesel::result::Error, _>(|| {
+            Self::set_password(&self.id, &new_credential.raw_password, &conn)?;
+            UserToken::revoke_all_except(&self.id, &current_family_id, &conn)?;
+            PersonalAccessToken::revoke_all(&self.id, &conn).map(|_| ())
+        })
+            .map_err(|_| ApiError::new(ApiErrorCode::ServerError, "Failed to change password."))
+    }
+    
+    pub fn set_password(user_id: &String, raw_password: &String, conn: &DBConnection) -> QueryResult<usize> {
+        use crate::schema::user_credentials::dsl;
+        
+        diesel::update(dsl::user_credentials.filter(dsl::id.eq(user_id)).filter(dsl::deleted_at.is_null()))
+            .set(dsl::password_hash.eq(hash_password(raw_password)))
+            .execute(conn)
+    }
+    
+    pub fn is_two_factor_enabled(&self) -> bool {
+        self.totp_enabled_at.is_some()
+    }
+    
+    pub fn start_two_factor_enrollment(&self, input: InputPassword, db: &DBConPool) -> Result<TwoFactorEnrollment, ApiError> {
+        use crate::schema::user_credentials::dsl;
+        
+        if self.is_two_factor_enabled() {
+            return Err(ApiError::new(ApiErrorCode::InvalidRequest, "Two-factor authentication is already enabled."));
+        }
+        if !self.verify_password(&input.password) {
+            return Err(ApiError::new(ApiErrorCode::AuthFailed, "Invalid credentials."));
+        }
+        
+        let secret = totp::generate_secret();
         
+        diesel::update(dsl::user_credentials.filter(dsl::id.eq(&self.id)).filter(dsl::totp_enabled_at.is_null()))
+            .set(dsl::totp_secret.eq(&secret))
+            .execute(&crate::get_db_connection(db))
+            .map_err(|_| ApiError::new(ApiErrorCode::ServerError, "Failed to start enrollment."))?;
+        
+        Ok(TwoFactorEnrollment { otpauth_uri: totp::provisioning_uri(&secret, &self.email), secret })
+    }
+    
+    /// Enables two-factor authentication once the first code matches, and returns the recovery codes.
+    pub fn enable_two_factor(&self, input: InputTwoFactorCode, db: &DBConPool) -> Result<Vec<String>, ApiError> {
+        use crate::schema::user_credentials::dsl;
+        
+        if self.is_two_factor_enabled() {
+            return Err(ApiError::new(ApiErrorCode::InvalidRequest, "Two-factor authentication is already enabled."));
+        }
+        let secret = match &self.totp_secret {
+            Some(s) => s,
+            None => return Err(ApiError::new(ApiErrorCode::InvalidRequest, "Start enrollment first."))
+        };
+        
+        let conn = crate::get_db_connection(db);
+        
+        let result = conn.transaction::<_, diesel::result::Error, _>(|| {
+            if !self.use_totp_code(secret, &input.code, &conn)? {
+                return Ok(None);
+            }
+            
+            diesel::update(dsl::user_credentials.filter(dsl::id.eq(&self.id)))
+                .set(dsl::totp_enabled_at.eq(get_now_naive_date_time()))
+                .execute(&conn)?;
+            
+            UserRecoveryCode::regenerate(&self.id, &conn).map(Some)
+        });
+        
+        match result {
+            Ok(Some(codes)) => Ok(codes),
+            Ok(None) => Err(ApiError::new(ApiErrorCode::InvalidRequest, "Invalid code.")),
+            Err(_) => Err(ApiError::new(ApiErrorCode::ServerError, "Failed to enable two-factor authentication.")),
+        }
+    }
+    
+    pub fn disable_two_factor(&self, input: InputPasswordAndCode, db: &DBConPool) -> Result<(), ApiError> {
+        use crate::schema::user_credentials::dsl;
+        
+        if !self.is_two_factor_enabled() {
+            return Err(ApiError::new(ApiErrorCode::InvalidRequest, "Two-factor authentication is not enabled."));
+        }
+        if !self.verify_password(&input.password) {
+            return Err(ApiError::new(ApiErrorCode::AuthFailed, "Invalid credentials."));
+        }
+        
+        let conn = crate::get_db_connection(db);
+        
+        let result = conn.transaction::<_, diesel::result::Error, _>(|| {
+            if !self.verify_second_factor(&input.code, &conn)? {
+                return Ok(false);
+            }
+            
+            diesel::update(dsl::user_credentials.filter(dsl::id.eq(&self.id)))
+                .set((
+                    dsl::totp_secret.eq(None::<String>),
+                    dsl::totp_enabled_at.eq(None::<chrono::NaiveDateTime>),
+                    dsl::totp_last_used_step.eq(None::<i64>),
+                ))
+                .execute(&conn)?;
+            UserRecoveryCode::delete_by_user(&self.id, &conn)?;
+            
+            Ok(true)
+        });
+        
+        match result {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(ApiError::new(ApiErrorCode::InvalidRequest, "Invalid code.")),
+            Err(_) => Err(ApiError::new(ApiErrorCode::ServerError, "Failed to disable two-factor authentication.")),
+        }
+    }
+    
+    /// Asks for the password as well, since a stolen session could otherwise guess TOTP codes here
+    /// until it gets a fresh set of recovery codes, which would outlive the session.
+    pub fn regenerate_recovery_codes(&self, input: InputPasswordAndCode, db: &DBConPool) -> Result<Vec<String>, ApiError> {
+        if !self.is_two_factor_enabled() {
+            return Err(ApiError::new(ApiErrorCode::InvalidRequest, "Two-factor authentication is not enabled."));
+        }
+        if !self.verify_password(&input.password) {
+            return Err(ApiError::new(ApiErrorCode::AuthFailed, "Invalid credentials."));
+        }
+        
+        let conn = crate::get_db_connection(db);
+        
+        let result = conn.transaction::<_, diesel::result::Error, _>(|| {
+            if !self.verify_second_factor(&input.code, &conn)? {
+                return Ok(None);
+            }
+            
+            UserRecoveryCode::regenerate(&self.id, &conn).map(Some)
+        });
+        
+        match result {
+            Ok(Some(codes)) => Ok(codes),
+            Ok(None) => Err(ApiError::new(ApiErrorCode::InvalidRequest, "Invalid code.")),
+            Err(_) => Err(ApiError::new(ApiErrorCode::ServerError, "Failed to regenerate recovery codes.")),
+        }
+    }
+    
+    /// Turning the magic link off also voids the links that were sent but not followed yet.
+    pub fn set_magic_link_enabled(&self, enabled: bool, db: &DBConPool) -> QueryResult<()> {
+        use crate::schema::user_credentials::dsl;
+        
+        let conn = crate::get_db_connection(db);
+        
+        conn.transaction(|| {
+            if !enabled {
+                MagicLinkToken::invalidate_by_user(&self.id, &conn)?;
+            }
+            diesel::update(dsl::user_credentials.filter(dsl::id.eq(&self.id)))
+                .set(dsl::magic_link_enabled.eq(enabled))
+                .execute(&conn)
+        })
+            .map(|_| ())
+    }
+    
+    /// Accepts either a TOTP code or an unused recovery code, which is used up.
+    pub fn verify_second_factor(&self, code: &str, conn: &DBConnection) -> QueryResult<bool> {
+        let secret = match &self.totp_secret {
+            Some(s) if self.is_two_factor_enabled() => s,
+            _ => return Ok(false)
+        };
+        
+        if self.use_totp_code(secret, code, conn)? {
+            return Ok(true);
+        }
+        
+        UserRecoveryCode::consume(&self.id, code, conn)
+    }
+    
+    fn use_totp_code(&self, secret: &str, code: &str, conn: &DBConnection) -> QueryResult<bool> {
+        use crate::schema::user_credentials::dsl;
+        
+        let step = match totp::verify(secret, code, get_now_date_time().timestamp()) {
+            Some(s) => s,
+            None => return Ok(false)
+        };
+        
+        // A code stays valid for a while, so the step is recorded to refuse a code that was seen already.
+        diesel::update(dsl::user_credentials
+            .filter(dsl::id.eq(&self.id))
+            .filter(dsl::totp_last_used_step.is_null().or(dsl::totp_last_used_step.lt(step)))
+        )
+            .set(dsl::totp_last_used_step.eq(step))
+            .execute(conn)
+            .map(|count| count > 0)
+    }
+    
+    fn verify_stored_credential(input_credential: &InputUserCredential, stored_credential: QueryResult<Self>, db: &DBConPool) -> Result<Self, ()> {
         match stored_credential {
-            Ok(Ok(c)) => Ok(c),
-            Ok(Err(_)) => Err(()),
+            Ok(c) if c.verify_password(&input_credential.raw_password) => {
+                c.rehash_if_outdated(&input_credential.raw_password, db);
+                Ok(c)
+            }
+            Ok(_) => Err(()),
             _ => {
-                std::thread::sleep(rand::thread_rng().gen_range(std::time::Duration::from_millis(5)..std::time::Duration::from_millis(50)));
+                // Unknown emails cost as much as a wrong password, so the timing does not tell whether an account exists.
+                let _ = PasswordHash::new(&dummy_password_hash())
+                    .and_then(|h| build_argon2().verify_password(input_credential.raw_password.as_bytes(), &h));
                 Err(())
             }
         }
     }
+    
+    /// The raw password is only at hand at login, so that is when a hash with outdated parameters is replaced.
+    fn rehash_if_outdated(&self, raw_password: &String, db: &DBConPool) {
+        use crate::schema::user_credentials::dsl;
+        
+        let is_outdated = PasswordHash::new(&self.password_hash)
+            .map(|h| h.algorithm.as_str() != Algorithm::Argon2id.as_str() || Params::try_from(&h).map_or(true, |p| !has_current_params(&p)))
+            .unwrap_or(false);
+        
+        if !is_outdated {
+            return;
+        }
+        
+        // The old hash is matched as well, so that a password changed in the meantime is not overwritten.
+        let result = diesel::update(dsl::user_credentials
+            .filter(dsl::id.eq(&self.id))
+            .filter(dsl::password_hash.eq(&self.password_hash))
+        )
+            .set(dsl::password_hash.eq(hash_password(raw_password)))
+            .execute(&crate::get_db_connection(db));
+        
+        if let Err(e) = result {
+            error!("failed to rehash password ({}): {:?}", self.id, e);
+        }
+    }
+    
+    pub fn delete_account(&self, db: &DBConPool) -> QueryResult<()> {
+        use crate::schema::user_credentials::dsl;
+        
+        let conn = crate::get_db_connection(db);
+        let deleted_at = get_now_naive_date_time();
+        
+        // Posts share the account's `deleted_at` so that exactly these posts come back on restore.
+        conn.transaction(|| {
+            diesel::delete(user_tokens::table.filter(user_tokens::user_id.eq(&self.id)))
+                .execute(&conn)?;
+            diesel::delete(user_refresh_tokens::table.filter(user_refresh_tokens::user_id.eq(&self.id)))
+                .execute(&conn)?;
+            diesel::delete(user_sessions::table.filter(user_sessions::user_id.eq(&self.id)))
+                .execute(&conn)?;
+            diesel::delete(personal_access_tokens::table.filter(personal_access_tokens::user_id.eq(&self.id)))
+                .execute(&conn)?;
+            diesel::delete(email_verification_tokens::table.filter(email_verification_tokens::user_id.eq(&self.id)))
+                .execute(&conn)?;
+            diesel::delete(email_change_tokens::table.filter(email_change_tokens::user_id.eq(&self.id)))
+                .execute(&conn)?;
+            diesel::delete(two_factor_challenges::table.filter(two_factor_challenges::user_id.eq(&self.id)))
+                .execute(&conn)?;
+            diesel::delete(password_reset_tokens::table.filter(password_reset_tokens::user_id.eq(&self.id)))
+                .execute(&conn)?;
+            diesel::delete(magic_link_tokens::table.filter(magic_link_tokens::user_id.eq(&self.id)))
+                .execute(&conn)?;
+            diesel::update(invite_codes::table.filter(invite_codes::inviter_id.eq(&self.id)).filter(invite_codes::deleted_at.is_null()))
+                .set(invite_codes::deleted_at.eq(deleted_at))
+                .execute(&conn)?;
+            diesel::update(posts::table.filter(posts::author_id.eq(&self.id)).filter(posts::deleted_at.is_null()))
+                .set(posts::deleted_at.eq(deleted_at))
+                .execute(&conn)?;
+            diesel::update(user_data_exports::table.filter(user_data_exports::user_id.eq(&self.id)).filter(user_data_exports::deleted_at.is_null()))
+                .set(user_data_exports::deleted_at.eq(deleted_at))
+                .execute(&conn)?;
+            diesel::update(users::table.filter(users::id.eq(&self.id)))
+                .set(users::deleted_at.eq(deleted_at))
+                .execute(&conn)?;
+            diesel::update(dsl::user_credentials.filter(dsl::id.eq(&self.id)))
+                .set(dsl::deleted_at.eq(deleted_at))
+                .execute(&conn)
+        })
+            .map(|_| ())
+    }
+    
+    pub fn restore_account(&self, db: &DBConPool) -> QueryResult<()> {
+        use crate::schema::user_credentials::dsl;
+        
+        let deleted_at = match self.deleted_at {
+            Some(d) => d,
+            None => return Ok(())
+        };
+        let conn = crate::get_db_connection(db);
+        
+        conn.transaction(|| {
+            diesel::update(posts::table.filter(posts::author_id.eq(&self.id)).filter(posts::deleted_at.eq(deleted_at)))
+                .set(posts::deleted_at.eq(None::<chrono::NaiveDateTime>))
+                .execute(&conn)?;
+            diesel::update(users::table.filter(users::id.eq(&self.id)))
+                .set(users::deleted_at.eq(None::<chrono::NaiveDateTime>))
+                .execute(&conn)?;
+            diesel::update(dsl::user_credentials.filter(dsl::id.eq(&self.id)))
+                .set(dsl::deleted_at.eq(None::<chrono::NaiveDateTime>))
+                .execute(&conn)
+        })
+            .map(|_| ())
+    }
+    
+    pub fn purge_deleted_accounts(db: &DBConPool) -> QueryResult<usize> {
+        use crate::schema::user_credentials::dsl;
+        
+        let conn = crate::get_db_connection(db);
+        
+        let expired_ids = dsl::user_credentials
+            .select(dsl::id)
+            .filter(dsl::deleted_at.lt(deletion_grace_period_start()))
+            .load::<String>(&conn)?;
+        
+        for id in &expired_ids {
+            conn.transaction(|| Self::purge_account(id, &conn))?;
+        }
+        
+        Ok(expired_ids.len())
+    }
+    
+    fn purge_account(id: &String, conn: &DBConnection) -> QueryResult<usize> {
+        use crate::schema::user_credentials::dsl;
+        
+        diesel::delete(user_tokens::table.filter(user_tokens::user_id.eq(id)))
+            .execute(conn)?;
+        diesel::delete(user_refresh_tokens::table.filter(user_refresh_tokens::user_id.eq(id)))
+            .execute(conn)?;
+        diesel::delete(user_sessions::table.filter(user_sessions::user_id.eq(id)))
+            .execute(conn)?;
+        diesel::delete(personal_access_tokens::table.filter(personal_access_tokens::user_id.eq(id)))
+            .execute(conn)?;
+        diesel::delete(email_verification_tokens::table.filter(email_verification_tokens::user_id.eq(id)))
+            .execute(conn)?;
+        diesel::delete(email_change_tokens::table.filter(email_change_tokens::user_id.eq(id)))
+            .execute(conn)?;
+        diesel::delete(two_factor_challenges::table.filter(two_factor_challenges::user_id.eq(id)))
+            .execute(conn)?;
+        diesel::delete(user_recovery_codes::table.filter(user_recovery_codes::user_id.eq(id)))
+            .execute(conn)?;
+        diesel::delete(password_reset_tokens::table.filter(password_reset_tokens::user_id.eq(id)))
+            .execute(conn)?;
+        diesel::delete(magic_link_tokens::table.filter(magic_link_tokens::user_id.eq(id)))
+            .execute(conn)?;
+        diesel::delete(security_events::table.filter(security_events::user_id.eq(id)))
+            .execute(conn)?;
+        diesel::update(dsl::user_credentials.filter(dsl::invited_by.eq(id)))
+            .set((dsl::invited_by.eq(None::<String>), dsl::invite_code_id.eq(None::<String>)))
+            .execute(conn)?;
+        diesel::delete(invite_codes::table.filter(invite_codes::inviter_id.eq(id)))
+            .execute(conn)?;
+        diesel::delete(oauth_authorization_codes::table.filter(oauth_authorization_codes::user_id.eq(id)))
+            .execute(conn)?;
+        OAuthApp::purge_by_owner(id, conn)?;
+        diesel::delete(post_import_errors::table.filter(
+            post_import_errors::import_id.eq_any(post_imports::table.select(post_imports::id).filter(post_imports::user_id.eq(id)))
+        ))
+            .execute(conn)?;
+        diesel::delete(post_imports::table.filter(post_imports::user_id.eq(id)))
+            .execute(conn)?;
+        diesel::delete(posts::table.filter(posts::author_id.eq(id)))
+            .execute(conn)?;
+        diesel::delete(user_images::table.filter(user_images::user_id.eq(id)))
+            .execute(conn)?;
+        diesel::delete(user_handle_histories::table.filter(user_handle_histories::user_id.eq(id)))
+            .execute(conn)?;
+        diesel::delete(user_data_exports::table.filter(user_data_exports::user_id.eq(id)))
+            .execute(conn)?;
+        diesel::delete(users::table.filter(users::id.eq(id)))
+            .execute(conn)?;
+        diesel::delete(dsl::user_credentials.filter(dsl::id.eq(id)))
+            .execute(conn)
+    }
+}
+
+fn deletion_grace_period_start() -> chrono::NaiveDateTime {
+    (get_now_date_time() - chrono::Duration::days(ACCOUNT_DELETION_GRACE_DAYS)).naive_local()
+}
+
+pub fn init_argon2_params() {
+    let env_or = |name: &str, default: u32| match std::env::var(name) {
+        Ok(v) => v.parse::<u32>().unwrap_or_else(|_| panic!("invalid {}", name)),
+        Err(_) => default,
+    };
+    
+    let params = Params::new(
+        env_or("ARGON2_MEMORY_KIB", DEFAULT_ARGON2_MEMORY_KIB),
+        env_or("ARGON2_ITERATIONS", DEFAULT_ARGON2_ITERATIONS),
+        env_or("ARGON2_PARALLELISM", DEFAULT_ARGON2_PARALLELISM),
+        None,
+    ).expect("invalid ARGON2_* parameters");
+    
+    ARGON2_PARAMS.set(params).expect("argon2 parameters are already initialized");
+}
+
+fn argon2_params() -> Params {
+    ARGON2_PARAMS.get().expect("argon2 parameters are not initialized").clone()
+}
+
+fn has_current_params(params: &Params) -> bool {
+    let current = argon2_params();
+    
+    params.m_cost() == current.m_cost() && params.t_cost() == current.t_cost() && params.p_cost() == current.p_cost()
 }
 
 fn build_argon2() -> Argon2<'static> {
     Argon2::new(
         Algorithm::Argon2id,
         Version::V0x13,
-        Params::new(37888, 1, 1, None).expect("Failed to initialize argon2"),
+        argon2_params(),
+    )
+}
+
+// Uses the current parameters, so that verifying against it takes as long as against a real hash.
+fn dummy_password_hash() -> String {
+    let params = argon2_params();
+    
+    format!(
+        "$argon2id$v=19$m={},t={},p={}$ZHVtbXlzYWx0ZHVtbXk$AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA",
+        params.m_cost(), params.t_cost(), params.p_cost()
     )
 }
 
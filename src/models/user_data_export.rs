@@ -0,0 +1,200 @@
+use diesel::prelude::*;
+
+use crate::DBConPool;
+use crate::models::{get_now_date_time, get_now_naive_date_time};
+use crate::models::error::{ApiError, ApiErrorCode};
+use crate::models::post::Post;
+use crate::models::user::User;
+use crate::models::user_credential::UserCredential;
+use crate::models::user_handle_history::UserHandleHistory;
+use crate::models::user_image::UserImage;
+use crate::models::user_session::UserSession;
+use crate::models::user_token::{generate_token, hash_token};
+use crate::schema::user_data_exports;
+
+const DOWNLOAD_TOKEN_PREFIX: &str = "rsns_dl_";
+const EXPORT_INTERVAL_DAYS: i64 = 7;
+const DOWNLOAD_VALIDITY_HOURS: i64 = 48;
+const PENDING_TIMEOUT_HOURS: i64 = 1;
+
+pub const STATUS_PENDING: &str = "pending";
+pub const STATUS_COMPLETED: &str = "completed";
+pub const STATUS_FAILED: &str = "failed";
+
+#[derive(Insertable)]
+#[table_name = "user_data_exports"]
+pub struct InsertableUserDataExport {
+    id: String,
+    user_id: String,
+    status: String,
+    download_token: String,
+}
+
+impl InsertableUserDataExport {
+    fn new(user_id: &String, download_token: &str) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: user_id.clone(),
+            status: STATUS_PENDING.to_string(),
+            download_token: hash_token(download_token),
+        }
+    }
+}
+
+#[derive(Serialize, Queryable, Clone)]
+pub struct UserDataExport {
+    pub id: String,
+    #[serde(skip)]
+    pub user_id: String,
+    pub status: String,
+    #[serde(skip)]
+    pub file_path: Option<String>,
+    #[serde(skip)]
+    pub download_token: Option<String>,
+    #[serde(serialize_with = "crate::models::serialize_option_naive_dt")]
+    pub expired_at: Option<chrono::NaiveDateTime>,
+    #[serde(serialize_with = "crate::models::serialize_naive_dt")]
+    pub created_at: chrono::NaiveDateTime,
+    #[serde(serialize_with = "crate::models::serialize_naive_dt")]
+    pub updated_at: chrono::NaiveDateTime,
+    #[serde(skip)]
+    pub deleted_at: Option<chrono::NaiveDateTime>,
+}
+
+/// The download token is only shown here; the export keeps its hash.
+#[derive(Serialize)]
+pub struct IssuedUserDataExport {
+    #[serde(flatten)]
+    pub export: UserDataExport,
+    download_token: String,
+}
+
+#[derive(Serialize)]
+pub struct UserDataArchive {
+    credential: UserCredential,
+    user: Option<User>,
+    posts: Vec<Post>,
+    handle_histories: Vec<UserHandleHistory>,
+    // Only the rows: the objects live in external storage this server has no client for.
+    images: Vec<UserImage>,
+    sessions: Vec<UserSession>,
+}
+
+impl UserDataArchive {
+    pub fn collect(user_id: &String, db: &DBConPool) -> QueryResult<Self> {
+        Ok(Self {
+            credential: UserCredential::fetch_by_id(user_id, db)?,
+            user: User::fetch_by_id(user_id, db).optional()?,
+            posts: Post::fetch_all_by_author(user_id, db)?,
+            handle_histories: UserHandleHistory::fetch_list_by_user(user_id, &crate::get_db_connection(db))?,
+            images: UserImage::fetch_list_by_user(user_id, db)?,
+            sessions: UserSession::fetch_list_by_user(user_id, db)?,
+        })
+    }
+}
+
+impl UserDataExport {
+    pub fn request(user_id: &String, db: &DBConPool) -> Result<IssuedUserDataExport, ApiError> {
+        use crate::schema::user_data_exports::dsl;
+        
+        let conn = crate::get_db_connection(db);
+        
+        let recent_export_count = dsl::user_data_exports
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::user_id.eq(user_id))
+            .filter(dsl::status.ne(STATUS_FAILED))
+            .filter(dsl::created_at.gt((get_now_date_time() - chrono::Duration::days(EXPORT_INTERVAL_DAYS)).naive_local()))
+            .count()
+            .get_result::<i64>(&conn)
+            .map_err(|_| ApiError::new(ApiErrorCode::ServerError, "Failed to request export."))?;
+        
+        if recent_export_count > 0 {
+            return Err(ApiError::new(ApiErrorCode::RateLimited, "Data can be exported only once a week."));
+        }
+        
+        let download_token = generate_token(DOWNLOAD_TOKEN_PREFIX);
+        let insertable_export = InsertableUserDataExport::new(user_id, &download_token);
+        
+        diesel::insert_into(dsl::user_data_exports)
+            .values(&insertable_export)
+            .execute(&conn)
+            .and_then(|_| Self::fetch_by_id(&insertable_export.id, user_id, db))
+            .map(|export| IssuedUserDataExport { export, download_token })
+            .map_err(|_| ApiError::new(ApiErrorCode::ServerError, "Failed to request export."))
+    }
+    
+    pub fn fetch_by_id(export_id: &String, user_id: &String, db: &DBConPool) -> QueryResult<Self> {
+        use crate::schema::user_data_exports::dsl;
+        
+        dsl::user_data_exports
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::id.eq(export_id))
+            .filter(dsl::user_id.eq(user_id))
+            .first::<Self>(&crate::get_db_connection(db))
+    }
+    
+    pub fn fetch_by_download_token(download_token: &String, db: &DBConPool) -> QueryResult<Self> {
+        use crate::schema::user_data_exports::dsl;
+        
+        dsl::user_data_exports
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::status.eq(STATUS_COMPLETED))
+            .filter(dsl::expired_at.gt(get_now_naive_date_time()))
+            .filter(dsl::download_token.eq(hash_token(download_token)))
+            .first::<Self>(&crate::get_db_connection(db))
+    }
+    
+    /// Also lists the exports of deleted accounts whose archives are still on disk.
+    pub fn fetch_expired_list(db: &DBConPool) -> QueryResult<Vec<Self>> {
+        use crate::schema::user_data_exports::dsl;
+        
+        dsl::user_data_exports
+            .filter(dsl::deleted_at.is_null().and(dsl::expired_at.lt(get_now_naive_date_time()))
+                .or(dsl::deleted_at.is_not_null().and(dsl::file_path.is_not_null())))
+            .load::<Self>(&crate::get_db_connection(db))
+    }
+    
+    pub fn complete(&self, file_path: &String, db: &DBConPool) -> QueryResult<usize> {
+        use crate::schema::user_data_exports::dsl;
+        
+        diesel::update(dsl::user_data_exports.filter(dsl::id.eq(&self.id)))
+            .set((
+                dsl::status.eq(STATUS_COMPLETED),
+                dsl::file_path.eq(file_path),
+                dsl::expired_at.eq((get_now_date_time() + chrono::Duration::hours(DOWNLOAD_VALIDITY_HOURS)).naive_local()),
+            ))
+            .execute(&crate::get_db_connection(db))
+    }
+    
+    pub fn fail(&self, db: &DBConPool) -> QueryResult<usize> {
+        use crate::schema::user_data_exports::dsl;
+        
+        diesel::update(dsl::user_data_exports.filter(dsl::id.eq(&self.id)))
+            .set(dsl::status.eq(STATUS_FAILED))
+            .execute(&crate::get_db_connection(db))
+    }
+    
+    /// Fails exports whose job never reported back, e.g. because the server restarted while it ran.
+    pub fn fail_stale_pending(db: &DBConPool) -> QueryResult<usize> {
+        use crate::schema::user_data_exports::dsl;
+        
+        diesel::update(dsl::user_data_exports
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::status.eq(STATUS_PENDING))
+            .filter(dsl::created_at.lt((get_now_date_time() - chrono::Duration::hours(PENDING_TIMEOUT_HOURS)).naive_local()))
+        )
+            .set(dsl::status.eq(STATUS_FAILED))
+            .execute(&crate::get_db_connection(db))
+    }
+    
+    pub fn delete(&self, db: &DBConPool) -> QueryResult<usize> {
+        use crate::schema::user_data_exports::dsl;
+        
+        diesel::update(dsl::user_data_exports.filter(dsl::id.eq(&self.id)))
+            .set((
+                dsl::file_path.eq(None::<String>),
+                dsl::deleted_at.eq(self.deleted_at.unwrap_or_else(get_now_naive_date_time)),
+            ))
+            .execute(&crate::get_db_connection(db))
+    }
+}
@@ -0,0 +1,99 @@
+use diesel::prelude::*;
+
+use crate::DBConnection;
+use crate::models::{get_now_date_time, get_now_naive_date_time};
+use crate::schema::user_handle_histories;
+
+const HANDLE_RESERVATION_DAYS: i64 = 30;
+
+#[derive(Insertable)]
+#[table_name = "user_handle_histories"]
+pub struct InsertableUserHandleHistory {
+    id: String,
+    user_id: String,
+    id_name: String,
+    reserved_until: chrono::NaiveDateTime,
+}
+
+impl InsertableUserHandleHistory {
+    fn new(user_id: &String, old_id_name: &String) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: user_id.clone(),
+            id_name: old_id_name.clone(),
+            reserved_until: (get_now_date_time() + chrono::Duration::days(HANDLE_RESERVATION_DAYS)).naive_local(),
+        }
+    }
+}
+
+#[derive(Serialize, Queryable)]
+pub struct UserHandleHistory {
+    pub id: String,
+    #[serde(skip)]
+    pub user_id: String,
+    pub id_name: String,
+    #[serde(serialize_with = "crate::models::serialize_naive_dt")]
+    pub reserved_until: chrono::NaiveDateTime,
+    #[serde(serialize_with = "crate::models::serialize_naive_dt")]
+    pub created_at: chrono::NaiveDateTime,
+    #[serde(skip)]
+    pub updated_at: chrono::NaiveDateTime,
+    #[serde(skip)]
+    pub deleted_at: Option<chrono::NaiveDateTime>,
+}
+
+impl UserHandleHistory {
+    pub fn record(user_id: &String, old_id_name: &String, conn: &DBConnection) -> QueryResult<usize> {
+        use crate::schema::user_handle_histories::dsl;
+        
+        diesel::insert_into(dsl::user_handle_histories)
+            .values(&InsertableUserHandleHistory::new(user_id, old_id_name))
+            .execute(conn)
+    }
+    
+    pub fn fetch_latest_by_user(user_id: &String, conn: &DBConnection) -> QueryResult<Self> {
+        use crate::schema::user_handle_histories::dsl;
+        
+        dsl::user_handle_histories
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::user_id.eq(user_id))
+            .order(dsl::created_at.desc())
+            .first::<Self>(conn)
+    }
+    
+    pub fn fetch_list_by_user(user_id: &String, conn: &DBConnection) -> QueryResult<Vec<Self>> {
+        use crate::schema::user_handle_histories::dsl;
+        
+        dsl::user_handle_histories
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::user_id.eq(user_id))
+            .order(dsl::created_at.desc())
+            .load::<Self>(conn)
+    }
+    
+    pub fn fetch_reserving_user_id(id_name: &str, conn: &DBConnection) -> QueryResult<String> {
+        use crate::schema::user_handle_histories::dsl;
+        
+        dsl::user_handle_histories
+            .select(dsl::user_id)
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::reserved_until.gt(get_now_naive_date_time()))
+            .filter(dsl::id_name.eq(id_name))
+            .order(dsl::created_at.desc())
+            .first::<String>(conn)
+    }    
+    /// Locking variant for handle changes, so that a reservation cannot appear between the check and the update.
+    pub fn lock_reserving_user_id(id_name: &str, conn: &DBConnection) -> QueryResult<Option<String>> {
+        use crate::schema::user_handle_histories::dsl;
+        
+        dsl::user_handle_histories
+            .select(dsl::user_id)
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::reserved_until.gt(get_now_naive_date_time()))
+            .filter(dsl::id_name.eq(id_name))
+            .order(dsl::created_at.desc())
+            .for_update()
+            .first::<String>(conn)
+            .optional()
+    }
+}
\ No newline at end of file
@@ -0,0 +1,30 @@
+use diesel::prelude::*;
+
+use crate::DBConPool;
+
+#[derive(Serialize, Queryable)]
+pub struct UserImage {
+    pub id: String,
+    #[serde(skip)]
+    pub user_id: Option<String>,
+    pub object_key: String,
+    pub image_type: String,
+    #[serde(serialize_with = "crate::models::serialize_option_naive_dt")]
+    pub created_at: Option<chrono::NaiveDateTime>,
+    #[serde(serialize_with = "crate::models::serialize_option_naive_dt")]
+    pub updated_at: Option<chrono::NaiveDateTime>,
+    #[serde(skip)]
+    pub deleted_at: Option<chrono::NaiveDateTime>,
+}
+
+impl UserImage {
+    pub fn fetch_list_by_user(user_id: &String, db: &DBConPool) -> QueryResult<Vec<Self>> {
+        use crate::schema::user_images::dsl;
+        
+        dsl::user_images
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::user_id.eq(user_id))
+            .order(dsl::created_at.asc())
+            .load::<Self>(&crate::get_db_connection(db))
+    }
+}
\ No newline at end of file
@@ -0,0 +1,71 @@
+use diesel::prelude::*;
+use rand::Rng;
+
+use crate::DBConnection;
+use crate::models::get_now_naive_date_time;
+use crate::models::user_token::hash_token;
+use crate::schema::user_recovery_codes;
+
+const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_CHARSET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+#[derive(Insertable)]
+#[table_name = "user_recovery_codes"]
+pub struct InsertableUserRecoveryCode {
+    code: String,
+    user_id: String,
+}
+
+pub struct UserRecoveryCode;
+
+impl UserRecoveryCode {
+    /// Replaces the codes of the user and returns the new ones, which are shown only this once.
+    pub fn regenerate(user_id: &String, conn: &DBConnection) -> QueryResult<Vec<String>> {
+        use crate::schema::user_recovery_codes::dsl;
+        
+        let raw_codes = (0..RECOVERY_CODE_COUNT).map(|_| generate_code()).collect::<Vec<String>>();
+        let insertable_codes = raw_codes.iter()
+            .map(|c| InsertableUserRecoveryCode { code: hash_token(&normalize_code(c)), user_id: user_id.clone() })
+            .collect::<Vec<InsertableUserRecoveryCode>>();
+        
+        Self::delete_by_user(user_id, conn)?;
+        diesel::insert_into(dsl::user_recovery_codes)
+            .values(&insertable_codes)
+            .execute(conn)
+            .map(|_| raw_codes)
+    }
+    
+    pub fn consume(user_id: &String, code: &str, conn: &DBConnection) -> QueryResult<bool> {
+        use crate::schema::user_recovery_codes::dsl;
+        
+        diesel::update(dsl::user_recovery_codes
+            .filter(dsl::code.eq(hash_token(&normalize_code(code))))
+            .filter(dsl::user_id.eq(user_id))
+            .filter(dsl::used_at.is_null())
+        )
+            .set(dsl::used_at.eq(get_now_naive_date_time()))
+            .execute(conn)
+            .map(|count| count > 0)
+    }
+    
+    pub fn delete_by_user(user_id: &String, conn: &DBConnection) -> QueryResult<usize> {
+        use crate::schema::user_recovery_codes::dsl;
+        
+        diesel::delete(dsl::user_recovery_codes.filter(dsl::user_id.eq(user_id)))
+            .execute(conn)
+    }
+}
+
+fn generate_code() -> String {
+    let mut rng = rand::thread_rng();
+    let mut pick = || RECOVERY_CODE_CHARSET[rng.gen_range(0..RECOVERY_CODE_CHARSET.len())] as char;
+    let first_half = (0..5).map(|_| pick()).collect::<String>();
+    let second_half = (0..5).map(|_| pick()).collect::<String>();
+    
+    format!("{}-{}", first_half, second_half)
+}
+
+// Codes are often typed by hand, so case and separators do not matter.
+fn normalize_code(code: &str) -> String {
+    code.chars().filter(|c| c.is_ascii_alphanumeric()).map(|c| c.to_ascii_lowercase()).collect()
+}
\ No newline at end of file
@@ -0,0 +1,124 @@
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::sql_types::Bool;
+
+use crate::{DBConnection, DBConPool};
+use crate::models::error::{ApiError, ApiErrorCode};
+use crate::models::{get_now_date_time, get_now_naive_date_time};
+use crate::models::user_session::UserSession;
+use crate::models::user_token::{generate_token, hash_token, IssuedUserToken, UserToken};
+use crate::schema::{user_refresh_tokens, user_sessions, user_tokens};
+
+pub const REFRESH_TOKEN_VALIDITY_DAYS: i64 = 30;
+const REFRESH_TOKEN_PREFIX: &str = "rsns_rt_";
+const LEGACY_TOKEN_LENGTH: usize = 36;
+
+#[derive(Deserialize)]
+pub struct InputRefreshToken {
+    pub refresh_token: String,
+}
+
+#[derive(Insertable)]
+#[table_name = "user_refresh_tokens"]
+pub struct InsertableUserRefreshToken {
+    token: String,
+    user_id: String,
+    family_id: String,
+    expired_at: chrono::NaiveDateTime,
+}
+
+impl InsertableUserRefreshToken {
+    fn new(user_id: &String, family_id: &String, raw_token: &str) -> Self {
+        Self {
+            token: hash_token(raw_token),
+            user_id: user_id.clone(),
+            family_id: family_id.clone(),
+            expired_at: (get_now_date_time() + chrono::Duration::days(REFRESH_TOKEN_VALIDITY_DAYS)).naive_local(),
+        }
+    }
+}
+
+#[derive(Queryable)]
+pub struct UserRefreshToken {
+    pub token: String,
+    pub user_id: String,
+    pub family_id: String,
+    pub app_id: Option<String>,
+}
+
+impl UserRefreshToken {
+    pub fn issue(user_id: &String, family_id: &String, conn: &DBConnection) -> QueryResult<String> {
+        use crate::schema::user_refresh_tokens::dsl;
+        
+        let raw_token = generate_token(REFRESH_TOKEN_PREFIX);
+        
+        diesel::insert_into(dsl::user_refresh_tokens)
+            .values(&InsertableUserRefreshToken::new(user_id, family_id, &raw_token))
+            .execute(conn)
+            .map(|_| raw_token)
+    }
+    
+    pub fn rotate(token: &String, app_id: Option<&String>, db: &DBConPool) -> Result<IssuedUserToken, ApiError> {
+        use crate::schema::user_refresh_tokens::dsl;
+        
+        let conn = crate::get_db_connection(db);
+        
+        let refresh_token = dsl::user_refresh_tokens
+            .inner_join(user_sessions::table.on(user_sessions::id.eq(dsl::family_id)))
+            .select((dsl::token, dsl::user_id, dsl::family_id, user_sessions::app_id))
+            .filter(dsl::expired_at.gt(get_now_naive_date_time()))
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::token.eq(hash_token(token)))
+            .first::<Self>(&conn)
+            .map_err(|_| ApiError::new(ApiErrorCode::InvalidToken, "Invalid refresh token."))?;
+        
+        if refresh_token.app_id.as_ref() != app_id {
+            return Err(ApiError::new(ApiErrorCode::InvalidToken, "Invalid refresh token."));
+        }
+        
+        // Only one request can mark the token as used, so a second use means it has leaked.
+        let result = conn.transaction::<_, diesel::result::Error, _>(|| {
+            let marked_count = diesel::update(dsl::user_refresh_tokens
+                .filter(dsl::token.eq(&refresh_token.token))
+                .filter(dsl::used_at.is_null())
+            )
+                .set(dsl::used_at.eq(get_now_naive_date_time()))
+                .execute(&conn)?;
+            
+            if marked_count == 0 {
+                UserToken::revoke_family(&refresh_token.family_id, &conn)?;
+                return Ok(None);
+            }
+            
+            diesel::delete(user_tokens::table.filter(user_tokens::family_id.eq(&refresh_token.family_id)))
+                .execute(&conn)?;
+            UserSession::extend(&refresh_token.family_id, &conn)?;
+            UserToken::issue_in_family(&refresh_token.user_id, &refresh_token.family_id, &conn).map(Some)
+        });
+        
+        match result {
+            Ok(Some(t)) => Ok(t),
+            Ok(None) => Err(ApiError::new(ApiErrorCode::InvalidToken, "Refresh token was already used. Please log in again.")),
+            Err(_) => Err(ApiError::new(ApiErrorCode::ServerError, "Failed to refresh token.")),
+        }
+    }
+    
+    pub fn hash_legacy_tokens(db: &DBConPool) -> QueryResult<usize> {
+        use crate::schema::user_refresh_tokens::dsl;
+        
+        let conn = crate::get_db_connection(db);
+        
+        let legacy_tokens = dsl::user_refresh_tokens
+            .select(dsl::token)
+            .filter(sql::<Bool>(&format!("CHAR_LENGTH(token) = {}", LEGACY_TOKEN_LENGTH)))
+            .load::<String>(&conn)?;
+        
+        for token in &legacy_tokens {
+            diesel::update(dsl::user_refresh_tokens.filter(dsl::token.eq(token)))
+                .set(dsl::token.eq(hash_token(token)))
+                .execute(&conn)?;
+        }
+        
+        Ok(legacy_tokens.len())
+    }
+}
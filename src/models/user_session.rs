@@ -0,0 +1,206 @@
+use diesel::prelude::*;
+
+use crate::{DBConnection, DBConPool};
+use crate::models::{get_now_date_time, get_now_naive_date_time};
+use crate::models::error::{ApiError, ApiErrorCode};
+use crate::models::token_scope::*;
+use crate::models::user_credential::InputUserCredential;
+use crate::models::user_refresh_token::REFRESH_TOKEN_VALIDITY_DAYS;
+use crate::models::user_token::UserToken;
+use crate::schema::user_sessions;
+
+const LAST_USED_UPDATE_INTERVAL_MINUTES: i64 = 1;
+
+#[derive(Deserialize)]
+pub struct InputLogin {
+    #[serde(flatten)]
+    pub credential: InputUserCredential,
+    pub device_name: Option<String>,
+}
+
+pub struct SessionMetadata {
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub device_name: Option<String>,
+}
+
+pub struct SessionGrant {
+    pub app_id: String,
+    pub scopes: Vec<TokenScope>,
+}
+
+#[derive(Insertable)]
+#[table_name = "user_sessions"]
+pub struct InsertableUserSession {
+    id: String,
+    user_id: String,
+    app_id: Option<String>,
+    scopes: Option<String>,
+    user_agent: Option<String>,
+    ip_address: Option<String>,
+    device_name: Option<String>,
+    expired_at: chrono::NaiveDateTime,
+}
+
+impl InsertableUserSession {
+    fn new(user_id: &String, metadata: &SessionMetadata, grant: Option<&SessionGrant>) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: user_id.clone(),
+            app_id: grant.map(|g| g.app_id.clone()),
+            scopes: grant.map(|g| join_scope_list(&g.scopes)),
+            user_agent: metadata.user_agent.as_ref().map(|a| a.chars().take(255).collect()),
+            ip_address: metadata.ip_address.clone(),
+            device_name: metadata.device_name.as_ref().map(|n| n.chars().take(100).collect()),
+            expired_at: session_expired_at(),
+        }
+    }
+}
+
+#[derive(Serialize, Queryable)]
+pub struct UserSession {
+    pub id: String,
+    #[serde(skip)]
+    pub user_id: String,
+    #[serde(skip)]
+    pub app_id: Option<String>,
+    #[serde(skip)]
+    pub scopes: Option<String>,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub device_name: Option<String>,
+    #[serde(serialize_with = "crate::models::serialize_naive_dt")]
+    pub last_used_at: chrono::NaiveDateTime,
+    #[serde(serialize_with = "crate::models::serialize_naive_dt")]
+    pub expired_at: chrono::NaiveDateTime,
+    #[serde(serialize_with = "crate::models::serialize_naive_dt")]
+    pub created_at: chrono::NaiveDateTime,
+    #[serde(skip)]
+    pub updated_at: chrono::NaiveDateTime,
+    #[serde(skip)]
+    pub deleted_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Serialize)]
+pub struct UserSessionForResponse {
+    #[serde(flatten)]
+    session: UserSession,
+    current: bool,
+}
+
+impl UserSession {
+    pub fn start(user_id: &String, metadata: &SessionMetadata, grant: Option<&SessionGrant>, conn: &DBConnection) -> QueryResult<String> {
+        use crate::schema::user_sessions::dsl;
+        
+        let insertable_session = InsertableUserSession::new(user_id, metadata, grant);
+        
+        diesel::insert_into(dsl::user_sessions)
+            .values(&insertable_session)
+            .execute(conn)
+            .map(|_| insertable_session.id)
+    }
+    
+    pub fn fetch_list_by_user(user_id: &String, db: &DBConPool) -> QueryResult<Vec<Self>> {
+        use crate::schema::user_sessions::dsl;
+        
+        dsl::user_sessions
+            .filter(dsl::expired_at.gt(get_now_naive_date_time()))
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::user_id.eq(user_id))
+            .filter(dsl::app_id.is_null())
+            .order(dsl::last_used_at.desc())
+            .load::<Self>(&crate::get_db_connection(db))
+    }
+    
+    pub fn fetch_by_id(session_id: &String, user_id: &String, db: &DBConPool) -> Result<Self, ApiError> {
+        use crate::schema::user_sessions::dsl;
+        
+        dsl::user_sessions
+            .filter(dsl::expired_at.gt(get_now_naive_date_time()))
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::id.eq(session_id))
+            .filter(dsl::user_id.eq(user_id))
+            .filter(dsl::app_id.is_null())
+            .first::<Self>(&crate::get_db_connection(db))
+            .map_err(|_| ApiError::new(ApiErrorCode::NotFound, "Session not found."))
+    }
+    
+    pub fn for_response(self, current_session_id: Option<&String>) -> UserSessionForResponse {
+        let current = current_session_id == Some(&self.id);
+        
+        UserSessionForResponse { session: self, current }
+    }
+    
+    pub fn extend(session_id: &String, conn: &DBConnection) -> QueryResult<usize> {
+        use crate::schema::user_sessions::dsl;
+        
+        diesel::update(dsl::user_sessions.filter(dsl::id.eq(session_id)))
+            .set((
+                dsl::last_used_at.eq(get_now_naive_date_time()),
+                dsl::expired_at.eq(session_expired_at()),
+            ))
+            .execute(conn)
+    }
+    
+    pub fn touch(session_id: &String, db: &DBConPool) -> QueryResult<usize> {
+        use crate::schema::user_sessions::dsl;
+        
+        let now = get_now_date_time();
+        
+        // Skip the write when the session was already marked recently.
+        diesel::update(dsl::user_sessions
+            .filter(dsl::id.eq(session_id))
+            .filter(dsl::last_used_at.lt((now - chrono::Duration::minutes(LAST_USED_UPDATE_INTERVAL_MINUTES)).naive_local()))
+        )
+            .set(dsl::last_used_at.eq(now.naive_local()))
+            .execute(&crate::get_db_connection(db))
+    }
+    
+    pub fn revoke(&self, db: &DBConPool) -> Result<(), ApiError> {
+        let conn = crate::get_db_connection(db);
+        
+        conn.transaction(|| UserToken::revoke_family(&self.id, &conn))
+            .map_err(|_| ApiError::new(ApiErrorCode::ServerError, "Failed to revoke session."))
+    }
+    
+    pub fn revoke_app_grants(app_id: &String, user_id: Option<&String>, conn: &DBConnection) -> QueryResult<usize> {
+        use crate::schema::user_sessions::dsl;
+        
+        let mut query = dsl::user_sessions
+            .select(dsl::id)
+            .filter(dsl::deleted_at.is_null())
+            .filter(dsl::app_id.eq(app_id))
+            .into_boxed();
+        
+        if let Some(user_id) = user_id {
+            query = query.filter(dsl::user_id.eq(user_id));
+        }
+        
+        let session_ids = query.load::<String>(conn)?;
+        
+        for session_id in &session_ids {
+            UserToken::revoke_family(session_id, conn)?;
+        }
+        
+        Ok(session_ids.len())
+    }
+    
+    pub fn end(session_id: &String, conn: &DBConnection) -> QueryResult<usize> {
+        use crate::schema::user_sessions::dsl;
+        
+        diesel::update(dsl::user_sessions.filter(dsl::id.eq(session_id)))
+            .set(dsl::deleted_at.eq(get_now_naive_date_time()))
+            .execute(conn)
+    }
+}
+
+fn session_expired_at() -> chrono::NaiveDateTime {
+    (get_now_date_time() + chrono::Duration::days(REFRESH_TOKEN_VALIDITY_DAYS)).naive_local()
+}
+
+pub fn session_scope_list(scopes: &Option<String>) -> Vec<TokenScope> {
+    match scopes {
+        Some(s) => parse_scope_list(s),
+        None => SESSION_SCOPES.to_vec(),
+    }
+}
\ No newline at end of file
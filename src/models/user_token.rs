@@ -1,12 +1,30 @@
+use diesel::dsl::sql;
 use diesel::prelude::*;
+use diesel::sql_types::Bool;
+use hmac::{Hmac, Mac, NewMac};
+use once_cell::sync::OnceCell;
+use rand::RngCore;
+use sha2::Sha256;
 
-use crate::DBConPool;
+use crate::{DBConnection, DBConPool};
 use crate::models::error::{ApiError, ApiErrorCode};
-use crate::models::get_now_date_time;
-use crate::schema::user_tokens;
+use crate::models::{get_now_date_time, get_now_naive_date_time};
+use crate::models::user_refresh_token::UserRefreshToken;
+use crate::models::token_scope::TokenScope;
+use crate::models::user_session::{session_scope_list, SessionGrant, SessionMetadata, UserSession};
+use crate::schema::{user_refresh_tokens, user_sessions, user_tokens};
+
+pub const ACCESS_TOKEN_VALIDITY_MINUTES: i64 = 15;
+const ACCESS_TOKEN_PREFIX: &str = "rsns_at_";
+const TOKEN_RANDOM_BYTES: usize = 32;
+const LEGACY_TOKEN_LENGTH: usize = 36;
+const MIN_TOKEN_HASH_KEY_LENGTH: usize = 32;
+
+static TOKEN_HASH_KEY: OnceCell<Vec<u8>> = OnceCell::new();
 
 struct NewUserToken {
     user_id: String,
+    family_id: String,
     validity_duration: chrono::Duration,
 }
 
@@ -15,17 +33,19 @@ struct NewUserToken {
 pub struct InsertableUserToken {
     token: String,
     user_id: String,
+    family_id: String,
     expired_at: chrono::NaiveDateTime,
     created_at: chrono::NaiveDateTime,
 }
 
 impl InsertableUserToken {
-    fn new(new_token: &NewUserToken) -> Self {
+    fn new(new_token: &NewUserToken, raw_token: &str) -> Self {
         let now_datetime = get_now_date_time();
         
         Self {
-            token: uuid::Uuid::new_v4().to_string(),
+            token: hash_token(raw_token),
             user_id: new_token.user_id.clone(),
+            family_id: new_token.family_id.clone(),
             expired_at: (now_datetime + new_token.validity_duration).naive_local(),
             created_at: now_datetime.naive_local(),
         }
@@ -34,9 +54,12 @@ impl InsertableUserToken {
 
 #[derive(Serialize, Queryable)]
 pub struct UserToken {
+    #[serde(skip)]
     pub token: String,
     #[serde(skip)]
     pub user_id: String,
+    #[serde(skip)]
+    pub family_id: String,
     #[serde(serialize_with = "crate::models::serialize_naive_dt")]
     pub expired_at: chrono::NaiveDateTime,
     #[serde(serialize_with = "crate::models::serialize_naive_dt")]
@@ -47,6 +70,16 @@ pub struct UserToken {
     pub deleted_at: Option<chrono::NaiveDateTime>,
 }
 
+#[derive(Serialize)]
+pub struct IssuedUserToken {
+    #[serde(skip)]
+    pub session_id: String,
+    pub token: String,
+    pub refresh_token: String,
+    #[serde(serialize_with = "crate::models::serialize_naive_dt")]
+    pub expired_at: chrono::NaiveDateTime,
+}
+
 macro_rules! filter_for_get_by_token {
     ($token:expr, $query:expr) => {
         $query
@@ -57,41 +90,188 @@ macro_rules! filter_for_get_by_token {
 }
 
 impl UserToken {
-    pub fn issue(user_id: &String, db: &DBConPool) -> Option<String> {
+    pub fn issue(user_id: &String, metadata: &SessionMetadata, db: &DBConPool) -> Option<IssuedUserToken> {
+        let conn = crate::get_db_connection(db);
+        
+        conn.transaction(|| Self::issue_for_session(user_id, metadata, None, &conn)).ok()
+    }
+    
+    pub fn issue_for_session(user_id: &String, metadata: &SessionMetadata, grant: Option<&SessionGrant>, conn: &DBConnection) -> QueryResult<IssuedUserToken> {
+        // A session is one token family, so its id doubles as the family id.
+        let session_id = UserSession::start(user_id, metadata, grant, conn)?;
+        Self::issue_in_family(user_id, &session_id, conn)
+    }
+    
+    pub fn issue_in_family(user_id: &String, family_id: &String, conn: &DBConnection) -> QueryResult<IssuedUserToken> {
         use crate::schema::user_tokens::dsl;
         
-        let user = NewUserToken { user_id: user_id.clone(), validity_duration: chrono::Duration::days(30) };
+        let new_token = NewUserToken {
+            user_id: user_id.clone(),
+            family_id: family_id.clone(),
+            validity_duration: chrono::Duration::minutes(ACCESS_TOKEN_VALIDITY_MINUTES),
+        };
         
-        let insertable_token = InsertableUserToken::new(&user);
+        let raw_token = generate_token(ACCESS_TOKEN_PREFIX);
+        let insertable_token = InsertableUserToken::new(&new_token, &raw_token);
         
-        let modified_rows_count = diesel::insert_into(dsl::user_tokens)
+        diesel::insert_into(dsl::user_tokens)
             .values(&insertable_token)
-            .execute(&crate::get_db_connection(db));
+            .execute(conn)?;
         
-        match modified_rows_count {
-            Ok(count) if count > 0 => {
-                Some(insertable_token.token)
-            }
-            _ => None
-        }
+        Ok(IssuedUserToken {
+            session_id: family_id.clone(),
+            token: raw_token,
+            refresh_token: UserRefreshToken::issue(user_id, family_id, conn)?,
+            expired_at: insertable_token.expired_at,
+        })
     }
     
     pub fn revoke(token: &String, db: &DBConPool) -> Result<(), ApiError> {
         use crate::schema::user_tokens::dsl;
         
-        filter_for_get_by_token!(token, diesel::delete(dsl::user_tokens))
-            .execute(&crate::get_db_connection(db))
+        let conn = crate::get_db_connection(db);
+        
+        let family_id = filter_for_get_by_token!(hash_token(token), dsl::user_tokens)
+            .select(dsl::family_id)
+            .first::<String>(&conn)
+            .map_err(|_| ApiError::new(ApiErrorCode::NotFound, "Token not found."))?;
+        
+        conn.transaction(|| Self::revoke_family(&family_id, &conn))
+            .map_err(|_| ApiError::new(ApiErrorCode::ServerError, "Failed to revoke token."))
+    }
+    
+    pub fn revoke_family(family_id: &String, conn: &DBConnection) -> QueryResult<()> {
+        diesel::delete(user_tokens::table.filter(user_tokens::family_id.eq(family_id)))
+            .execute(conn)?;
+        diesel::update(user_refresh_tokens::table.filter(user_refresh_tokens::family_id.eq(family_id)))
+            .set(user_refresh_tokens::deleted_at.eq(get_now_naive_date_time()))
+            .execute(conn)?;
+        UserSession::end(family_id, conn)
             .map(|_| ())
+    }
+    
+    pub fn fetch_family_id(token: &String, conn: &DBConnection) -> Result<String, ApiError> {
+        use crate::schema::user_tokens::dsl;
+        
+        dsl::user_tokens
+            .select(dsl::family_id)
+            .filter(dsl::token.eq(hash_token(token)))
+            .first::<String>(conn)
             .map_err(|_| ApiError::new(ApiErrorCode::NotFound, "Token not found."))
     }
     
-    pub fn verify_token(token: &String, db: &DBConPool) -> Result<Self, ApiError> {
+    pub fn revoke_others(user_id: &String, current_token: &String, db: &DBConPool) -> Result<(), ApiError> {
+        let conn = crate::get_db_connection(db);
+        
+        let current_family_id = Self::fetch_family_id(current_token, &conn)?;
+        
+        // Apps the user has authorized are not login sessions, so they stay connected.
+        conn.transaction::<_, diesel::result::Error, _>(|| {
+            let other_session_ids = user_sessions::table
+                .select(user_sessions::id)
+                .filter(user_sessions::deleted_at.is_null())
+                .filter(user_sessions::user_id.eq(user_id))
+                .filter(user_sessions::app_id.is_null())
+                .filter(user_sessions::id.ne(&current_family_id))
+                .load::<String>(&conn)?;
+            
+            for session_id in &other_session_ids {
+                Self::revoke_family(session_id, &conn)?;
+            }
+            
+            Ok(())
+        })
+            .map_err(|_| ApiError::new(ApiErrorCode::ServerError, "Failed to revoke tokens."))
+    }
+    
+    /// Revokes every session of the user but the given one, including the ones of authorized apps.
+    pub fn revoke_all_except(user_id: &String, current_family_id: &String, conn: &DBConnection) -> QueryResult<()> {
+        let session_ids = user_sessions::table
+            .select(user_sessions::id)
+            .filter(user_sessions::deleted_at.is_null())
+            .filter(user_sessions::user_id.eq(user_id))
+            .filter(user_sessions::id.ne(current_family_id))
+            .load::<String>(conn)?;
+        
+        for session_id in &session_ids {
+            Self::revoke_family(session_id, conn)?;
+        }
+        
+        Ok(())
+    }
+    
+    /// Revokes every session of the user, including the ones of authorized apps.
+    pub fn revoke_all(user_id: &String, conn: &DBConnection) -> QueryResult<()> {
+        let session_ids = user_sessions::table
+            .select(user_sessions::id)
+            .filter(user_sessions::deleted_at.is_null())
+            .filter(user_sessions::user_id.eq(user_id))
+            .load::<String>(conn)?;
+        
+        for session_id in &session_ids {
+            Self::revoke_family(session_id, conn)?;
+        }
+        
+        diesel::delete(user_tokens::table.filter(user_tokens::user_id.eq(user_id)))
+            .execute(conn)
+            .map(|_| ())
+    }
+    
+    pub fn verify_token(token: &String, db: &DBConPool) -> Result<(Self, Vec<TokenScope>), ApiError> {
         use crate::schema::user_tokens::dsl;
         
-        let token: QueryResult<Self> = filter_for_get_by_token!(token, dsl::user_tokens)
-            .first::<Self>(&crate::get_db_connection(db));
+        let token: QueryResult<(Self, Option<String>)> = filter_for_get_by_token!(hash_token(token), dsl::user_tokens)
+            .inner_join(user_sessions::table.on(user_sessions::id.eq(dsl::family_id)))
+            .filter(user_sessions::deleted_at.is_null())
+            .select((user_tokens::all_columns, user_sessions::scopes))
+            .first::<(Self, Option<String>)>(&crate::get_db_connection(db));
         
         token
+            .map(|(t, scopes)| (t, session_scope_list(&scopes)))
             .map_err(|_| ApiError::new(ApiErrorCode::InvalidToken, "Invalid token."))
     }
-}
\ No newline at end of file
+    
+    pub fn hash_legacy_tokens(db: &DBConPool) -> QueryResult<usize> {
+        use crate::schema::user_tokens::dsl;
+        
+        let conn = crate::get_db_connection(db);
+        
+        let legacy_tokens = dsl::user_tokens
+            .select(dsl::token)
+            .filter(sql::<Bool>(&format!("CHAR_LENGTH(token) = {}", LEGACY_TOKEN_LENGTH)))
+            .load::<String>(&conn)?;
+        
+        for token in &legacy_tokens {
+            diesel::update(dsl::user_tokens.filter(dsl::token.eq(token)))
+                .set(dsl::token.eq(hash_token(token)))
+                .execute(&conn)?;
+        }
+        
+        Ok(legacy_tokens.len())
+    }
+}
+
+pub fn generate_token(prefix: &str) -> String {
+    let mut bytes = [0u8; TOKEN_RANDOM_BYTES];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    
+    format!("{}{}", prefix, base64::encode_config(bytes, base64::URL_SAFE_NO_PAD))
+}
+
+pub fn init_token_hash_key() {
+    let key = std::env::var("TOKEN_HASH_KEY").expect("invalid TOKEN_HASH_KEY");
+    
+    if key.len() < MIN_TOKEN_HASH_KEY_LENGTH {
+        panic!("TOKEN_HASH_KEY must be at least {} bytes long", MIN_TOKEN_HASH_KEY_LENGTH);
+    }
+    
+    TOKEN_HASH_KEY.set(key.into_bytes()).expect("TOKEN_HASH_KEY is already initialized");
+}
+
+pub fn hash_token(token: &str) -> String {
+    let key = TOKEN_HASH_KEY.get().expect("TOKEN_HASH_KEY is not initialized");
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(token.as_bytes());
+    
+    format!("{:x}", mac.finalize().into_bytes())
+}
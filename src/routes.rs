@@ -1,30 +1,71 @@
+use actix_web::http::Method;
 use actix_web::web;
 
-use crate::controllers::{auth_controller, post_controller, user_controller};
+const IMPORT_PAYLOAD_LIMIT: usize = 50 * 1024 * 1024;
+
+use crate::controllers::{admin_controller, auth_controller, export_controller, import_controller, invite_code_controller, oauth_controller, personal_access_token_controller, post_controller, search_controller, security_event_controller, session_controller, two_factor_controller, user_controller};
+use crate::models::token_scope::TokenScope;
 use crate::services::token_authentication::TokenAuthentication;
 
 pub fn users(cfg: &mut web::ServiceConfig) {
     cfg
         .service(web::scope("/users")
             .service(web::resource("/me")
-                .wrap(TokenAuthentication::required())
+                .wrap(TokenAuthentication::required()
+                    .require_scope(Method::GET, TokenScope::Read)
+                    .require_scope(Method::PATCH, TokenScope::WriteProfile)
+                )
                 .route(web::get().to(user_controller::show_me))
                 .route(web::patch().to(user_controller::update_me))
+                .route(web::delete().to(user_controller::delete_me))
             )
-            .service(web::resource("/me/posts")
+            .service(web::resource("/me/deactivate")
+                .wrap(TokenAuthentication::required())
+                .route(web::post().to(user_controller::deactivate_me))
+            )
+            .service(web::resource("/me/exports")
+                .wrap(TokenAuthentication::required())
+                .route(web::post().to(export_controller::create))
+            )
+            .service(web::resource("/me/exports/{id}")
+                .wrap(TokenAuthentication::required())
+                .route(web::get().to(export_controller::show))
+            )
+            .service(web::resource("/me/imports")
+                .wrap(TokenAuthentication::required())
+                .app_data(web::PayloadConfig::new(IMPORT_PAYLOAD_LIMIT))
+                .route(web::post().to(import_controller::create))
+            )
+            .service(web::resource("/me/imports/{id}")
                 .wrap(TokenAuthentication::required())
+                .route(web::get().to(import_controller::show))
+            )
+            .service(web::resource("/me/id_name")
+                .wrap(TokenAuthentication::required().require_scope(Method::PATCH, TokenScope::WriteProfile))
+                .route(web::patch().to(user_controller::update_my_id_name))
+            )
+            .service(web::resource("/me/posts")
+                .wrap(TokenAuthentication::required().require_scope(Method::GET, TokenScope::Read))
                 .route(web::get().to(post_controller::my_index))
             )
+            .service(web::resource("/by_name/{id_name}")
+                .wrap(TokenAuthentication::unnecessary().require_scope(Method::GET, TokenScope::Read))
+                .route(web::get().to(user_controller::show_by_name))
+            )
+            .service(web::resource("/by_name/{id_name}/posts")
+                .wrap(TokenAuthentication::required().require_scope(Method::GET, TokenScope::Read))
+                .route(web::get().to(post_controller::users_index_by_name))
+            )
             .service(web::resource("/{id}")
-                .wrap(TokenAuthentication::unnecessary())
+                .wrap(TokenAuthentication::unnecessary().require_scope(Method::GET, TokenScope::Read))
                 .route(web::get().to(user_controller::show))
             )
             .service(web::resource("")
-                .wrap(TokenAuthentication::required())
+                .wrap(TokenAuthentication::required().require_scope(Method::POST, TokenScope::WriteProfile))
                 .route(web::post().to(user_controller::create))
             )
             .service(web::resource("/{id}/posts")
-                .wrap(TokenAuthentication::required())
+                .wrap(TokenAuthentication::required().require_scope(Method::GET, TokenScope::Read))
                 .route(web::get().to(post_controller::users_index))
             )
         );
@@ -33,7 +74,11 @@ pub fn users(cfg: &mut web::ServiceConfig) {
 pub fn posts(cfg: &mut web::ServiceConfig) {
     cfg
         .service(web::scope("/posts")
-            .wrap(TokenAuthentication::required())
+            .wrap(TokenAuthentication::required()
+                .require_scope(Method::GET, TokenScope::Read)
+                .require_scope(Method::POST, TokenScope::WritePosts)
+                .require_scope(Method::DELETE, TokenScope::WritePosts)
+            )
             .service(web::resource("")
                 .route(web::get().to(post_controller::index))
                 .route(web::post().to(post_controller::create))
@@ -49,7 +94,21 @@ pub fn auth(cfg: &mut web::ServiceConfig) {
     cfg
         .service(web::scope("/auth")
             .route("/login", web::post().to(auth_controller::login))
+            .route("/login/two_factor", web::post().to(auth_controller::login_two_factor))
+            .route("/login/magic_link", web::post().to(auth_controller::login_magic_link))
+            .route("/magic_link", web::post().to(auth_controller::request_magic_link))
             .route("/register", web::post().to(auth_controller::register))
+            .route("/registration_mode", web::get().to(auth_controller::show_registration_mode))
+            .route("/restore", web::post().to(auth_controller::restore))
+            .route("/refresh", web::post().to(auth_controller::refresh))
+            .route("/verify_email", web::post().to(auth_controller::verify_email))
+            .route("/password/forgot", web::post().to(auth_controller::forgot_password))
+            .route("/password/reset", web::post().to(auth_controller::reset_password))
+            .route("/confirm_email_change", web::post().to(auth_controller::confirm_email_change))
+            .service(web::resource("/verify_email/resend")
+                .wrap(TokenAuthentication::required())
+                .route(web::post().to(auth_controller::resend_verification_email))
+            )
             .service(web::resource("/logout")
                 .wrap(TokenAuthentication::required())
                 .route(web::delete().to(auth_controller::logout))
@@ -58,5 +117,123 @@ pub fn auth(cfg: &mut web::ServiceConfig) {
                 .wrap(TokenAuthentication::required())
                 .route(web::get().to(auth_controller::show_me))
             )
+            .service(web::resource("/me/password")
+                .wrap(TokenAuthentication::required())
+                .route(web::put().to(auth_controller::change_password))
+            )
+            .service(web::resource("/me/email")
+                .wrap(TokenAuthentication::required())
+                .route(web::put().to(auth_controller::request_email_change))
+            )
+            .service(web::resource("/me/magic_link")
+                .wrap(TokenAuthentication::required())
+                .route(web::put().to(auth_controller::update_magic_link_setting))
+            )
+            .service(web::resource("/two_factor")
+                .wrap(TokenAuthentication::required())
+                .route(web::post().to(two_factor_controller::enroll))
+                .route(web::delete().to(two_factor_controller::disable))
+            )
+            .service(web::resource("/two_factor/confirm")
+                .wrap(TokenAuthentication::required())
+                .route(web::post().to(two_factor_controller::confirm))
+            )
+            .service(web::resource("/two_factor/recovery_codes")
+                .wrap(TokenAuthentication::required())
+                .route(web::post().to(two_factor_controller::regenerate_recovery_codes))
+            )
+            .service(web::resource("/sessions")
+                .wrap(TokenAuthentication::required())
+                .route(web::get().to(session_controller::index))
+                .route(web::delete().to(session_controller::destroy_others))
+            )
+            .service(web::resource("/sessions/{id}")
+                .wrap(TokenAuthentication::required())
+                .route(web::delete().to(session_controller::destroy))
+            )
+            .service(web::resource("/security_events")
+                .wrap(TokenAuthentication::required())
+                .route(web::get().to(security_event_controller::index))
+            )
+            .service(web::resource("/tokens")
+                .wrap(TokenAuthentication::required())
+                .route(web::get().to(personal_access_token_controller::index))
+                .route(web::post().to(personal_access_token_controller::create))
+            )
+            .service(web::resource("/tokens/{id}")
+                .wrap(TokenAuthentication::required())
+                .route(web::delete().to(personal_access_token_controller::destroy))
+            )
+            .service(web::resource("/invite_codes")
+                .wrap(TokenAuthentication::required())
+                .route(web::get().to(invite_code_controller::index))
+                .route(web::post().to(invite_code_controller::create))
+            )
+            .service(web::resource("/invite_codes/{id}")
+                .wrap(TokenAuthentication::required())
+                .route(web::delete().to(invite_code_controller::destroy))
+            )
+        );
+}
+
+pub fn admin(cfg: &mut web::ServiceConfig) {
+    cfg
+        .service(web::scope("/admin")
+            .wrap(TokenAuthentication::required())
+            .service(web::resource("/registrations")
+                .route(web::get().to(admin_controller::registrations_index))
+            )
+            .service(web::resource("/registrations/{id}")
+                .route(web::delete().to(admin_controller::reject_registration))
+            )
+            .service(web::resource("/registrations/{id}/approve")
+                .route(web::post().to(admin_controller::approve_registration))
+            )
+        );
+}
+
+pub fn oauth(cfg: &mut web::ServiceConfig) {
+    cfg
+        .service(web::scope("/oauth")
+            .route("/token", web::post().to(oauth_controller::token))
+            .route("/revoke", web::post().to(oauth_controller::revoke))
+            .service(web::resource("/authorize")
+                .wrap(TokenAuthentication::required())
+                .route(web::get().to(oauth_controller::show_authorization_request))
+                .route(web::post().to(oauth_controller::authorize))
+            )
+            .service(web::resource("/apps")
+                .wrap(TokenAuthentication::required())
+                .route(web::get().to(oauth_controller::apps_index))
+                .route(web::post().to(oauth_controller::apps_create))
+            )
+            .service(web::resource("/apps/{id}")
+                .wrap(TokenAuthentication::required())
+                .route(web::delete().to(oauth_controller::apps_destroy))
+            )
+            .service(web::resource("/authorizations")
+                .wrap(TokenAuthentication::required())
+                .route(web::get().to(oauth_controller::authorizations_index))
+            )
+            .service(web::resource("/authorizations/{id}")
+                .wrap(TokenAuthentication::required())
+                .route(web::delete().to(oauth_controller::authorizations_destroy))
+            )
+        );
+}
+
+pub fn search(cfg: &mut web::ServiceConfig) {
+    cfg
+        .service(web::scope("/search")
+            .wrap(TokenAuthentication::unnecessary().require_scope(Method::GET, TokenScope::Read))
+            .route("/users", web::get().to(search_controller::users))
+            .route("/posts", web::get().to(search_controller::posts))
+        );
+}
+
+pub fn exports(cfg: &mut web::ServiceConfig) {
+    cfg
+        .service(web::scope("/exports")
+            .route("/{download_token}", web::get().to(export_controller::download))
         );
 }
\ No newline at end of file
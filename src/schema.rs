@@ -1,3 +1,120 @@
+table! {
+    auth_throttles (throttle_key) {
+        throttle_key -> Char,
+        failed_count -> Integer,
+        locked_until -> Nullable<Timestamp>,
+        last_failed_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    email_change_tokens (token) {
+        token -> Char,
+        user_id -> Char,
+        new_email -> Varchar,
+        expired_at -> Timestamp,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        deleted_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    email_verification_tokens (token) {
+        token -> Char,
+        user_id -> Char,
+        email -> Varchar,
+        expired_at -> Timestamp,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        deleted_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    invite_codes (id) {
+        id -> Char,
+        inviter_id -> Char,
+        code -> Char,
+        max_uses -> Integer,
+        used_count -> Integer,
+        expired_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        deleted_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    magic_link_tokens (token) {
+        token -> Char,
+        user_id -> Char,
+        device_name -> Nullable<Varchar>,
+        expired_at -> Timestamp,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        deleted_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    oauth_apps (id) {
+        id -> Char,
+        owner_id -> Char,
+        name -> Varchar,
+        client_secret -> Nullable<Char>,
+        redirect_uris -> Varchar,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        deleted_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    oauth_authorization_codes (code) {
+        code -> Char,
+        app_id -> Char,
+        user_id -> Char,
+        redirect_uri -> Varchar,
+        scopes -> Varchar,
+        code_challenge -> Varchar,
+        session_id -> Nullable<Char>,
+        used_at -> Nullable<Timestamp>,
+        expired_at -> Timestamp,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        deleted_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    password_reset_tokens (token) {
+        token -> Char,
+        user_id -> Char,
+        expired_at -> Timestamp,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        deleted_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    personal_access_tokens (id) {
+        id -> Char,
+        user_id -> Char,
+        name -> Varchar,
+        token -> Char,
+        scopes -> Varchar,
+        last_used_at -> Nullable<Timestamp>,
+        expired_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        deleted_at -> Nullable<Timestamp>,
+    }
+}
+
 table! {
     posts (id) {
         id -> Char,
@@ -7,6 +124,7 @@ table! {
         updated_at -> Timestamp,
         published_at -> Nullable<Timestamp>,
         deleted_at -> Nullable<Timestamp>,
+        import_source_id -> Nullable<Varchar>,
     }
 }
 
@@ -22,6 +140,33 @@ table! {
         created_at -> Timestamp,
         updated_at -> Timestamp,
         deleted_at -> Nullable<Timestamp>,
+        deactivated_at -> Nullable<Timestamp>,
+        version -> Integer,
+    }
+}
+
+table! {
+    security_events (id) {
+        id -> Bigint,
+        user_id -> Char,
+        event_type -> Varchar,
+        detail -> Nullable<Varchar>,
+        ip_address -> Nullable<Varchar>,
+        user_agent -> Nullable<Varchar>,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    two_factor_challenges (token) {
+        token -> Char,
+        user_id -> Char,
+        device_name -> Nullable<Varchar>,
+        failed_attempts -> Integer,
+        expired_at -> Timestamp,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        deleted_at -> Nullable<Timestamp>,
     }
 }
 
@@ -30,6 +175,15 @@ table! {
         id -> Char,
         password_hash -> Varchar,
         email -> Varchar,
+        email_verified_at -> Nullable<Timestamp>,
+        totp_secret -> Nullable<Varchar>,
+        totp_enabled_at -> Nullable<Timestamp>,
+        totp_last_used_step -> Nullable<Bigint>,
+        magic_link_enabled -> Bool,
+        is_admin -> Bool,
+        approved_at -> Nullable<Timestamp>,
+        invited_by -> Nullable<Char>,
+        invite_code_id -> Nullable<Char>,
         created_at -> Timestamp,
         updated_at -> Timestamp,
         deleted_at -> Nullable<Timestamp>,
@@ -48,10 +202,50 @@ table! {
     }
 }
 
+table! {
+    user_recovery_codes (code) {
+        code -> Char,
+        user_id -> Char,
+        used_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    user_refresh_tokens (token) {
+        token -> Char,
+        user_id -> Char,
+        family_id -> Char,
+        used_at -> Nullable<Timestamp>,
+        expired_at -> Timestamp,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        deleted_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    user_sessions (id) {
+        id -> Char,
+        user_id -> Char,
+        app_id -> Nullable<Char>,
+        scopes -> Nullable<Varchar>,
+        user_agent -> Nullable<Varchar>,
+        ip_address -> Nullable<Varchar>,
+        device_name -> Nullable<Varchar>,
+        last_used_at -> Timestamp,
+        expired_at -> Timestamp,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        deleted_at -> Nullable<Timestamp>,
+    }
+}
+
 table! {
     user_tokens (token) {
         token -> Char,
         user_id -> Char,
+        family_id -> Char,
         expired_at -> Timestamp,
         created_at -> Timestamp,
         updated_at -> Timestamp,
@@ -59,14 +253,103 @@ table! {
     }
 }
 
+table! {
+    user_handle_histories (id) {
+        id -> Char,
+        user_id -> Char,
+        id_name -> Varchar,
+        reserved_until -> Timestamp,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        deleted_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    user_data_exports (id) {
+        id -> Char,
+        user_id -> Char,
+        status -> Varchar,
+        file_path -> Nullable<Varchar>,
+        download_token -> Nullable<Char>,
+        expired_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        deleted_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    post_imports (id) {
+        id -> Char,
+        user_id -> Char,
+        format -> Varchar,
+        status -> Varchar,
+        total_count -> Integer,
+        imported_count -> Integer,
+        skipped_count -> Integer,
+        failed_count -> Integer,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        deleted_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    post_import_errors (id) {
+        id -> Char,
+        import_id -> Char,
+        row_number -> Integer,
+        message -> Varchar,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        deleted_at -> Nullable<Timestamp>,
+    }
+}
+
 joinable!(posts -> users (author_id));
+joinable!(email_change_tokens -> user_credentials (user_id));
+joinable!(email_verification_tokens -> user_credentials (user_id));
+joinable!(invite_codes -> user_credentials (inviter_id));
+joinable!(magic_link_tokens -> user_credentials (user_id));
+joinable!(oauth_apps -> user_credentials (owner_id));
+joinable!(oauth_authorization_codes -> oauth_apps (app_id));
+joinable!(password_reset_tokens -> user_credentials (user_id));
+joinable!(personal_access_tokens -> user_credentials (user_id));
+joinable!(security_events -> user_credentials (user_id));
+joinable!(two_factor_challenges -> user_credentials (user_id));
+joinable!(user_recovery_codes -> user_credentials (user_id));
+joinable!(user_refresh_tokens -> user_credentials (user_id));
+joinable!(user_sessions -> user_credentials (user_id));
 joinable!(user_tokens -> user_credentials (user_id));
 joinable!(users -> user_credentials (id));
+joinable!(user_handle_histories -> users (user_id));
+joinable!(user_data_exports -> user_credentials (user_id));
+joinable!(post_imports -> users (user_id));
+joinable!(post_import_errors -> post_imports (import_id));
 
 allow_tables_to_appear_in_same_query!(
+    auth_throttles,
+    email_change_tokens,
+    email_verification_tokens,
+    invite_codes,
+    magic_link_tokens,
+    oauth_apps,
+    oauth_authorization_codes,
+    password_reset_tokens,
+    personal_access_tokens,
     posts,
+    security_events,
+    two_factor_challenges,
     users,
     user_credentials,
     user_images,
+    user_recovery_codes,
+    user_refresh_tokens,
+    user_sessions,
     user_tokens,
+    user_handle_histories,
+    user_data_exports,
+    post_imports,
+    post_import_errors,
 );
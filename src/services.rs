@@ -1 +1,9 @@
-pub mod token_authentication;
\ No newline at end of file
+pub mod account_purge;
+pub mod data_export;
+pub mod mailer;
+pub mod password_hashing;
+pub mod post_import;
+pub mod session_cookie;
+pub mod token_authentication;
+pub mod token_migration;
+pub mod trusted_proxy;
\ No newline at end of file
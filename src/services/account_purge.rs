@@ -0,0 +1,43 @@
+use std::time::Duration;
+
+use actix_web::{rt, web};
+use log::{error, info};
+
+use crate::DBConPool;
+use crate::models::auth_throttle::AuthThrottle;
+use crate::models::security_event::SecurityEvent;
+use crate::models::user_credential::UserCredential;
+use crate::models::user_data_export::UserDataExport;
+use crate::services::data_export;
+
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+pub fn spawn(db: DBConPool) {
+    rt::spawn(async move {
+        let mut interval = rt::time::interval(PURGE_INTERVAL);
+        
+        loop {
+            interval.tick().await;
+            
+            run_step("remove expired data exports", data_export::remove_expired_archives, &db).await;
+            run_step("fail stale pending data exports", UserDataExport::fail_stale_pending, &db).await;
+            run_step("remove stale auth throttles", AuthThrottle::purge_stale, &db).await;
+            run_step("remove expired security events", SecurityEvent::purge_expired, &db).await;
+            run_step("purge deleted accounts", UserCredential::purge_deleted_accounts, &db).await;
+        }
+    });
+}
+
+// Each step runs on the blocking pool, so that a long purge does not stall the worker serving requests.
+async fn run_step<E>(task: &'static str, step: fn(&DBConPool) -> Result<usize, E>, db: &DBConPool)
+    where
+        E: std::fmt::Debug + Send + 'static
+{
+    let db = db.clone();
+    
+    match web::block(move || step(&db)).await {
+        Ok(count) if count > 0 => info!("{}: {} rows affected", task, count),
+        Ok(_) => {}
+        Err(e) => error!("failed to {}: {:?}", task, e),
+    }
+}
\ No newline at end of file
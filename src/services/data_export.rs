@@ -0,0 +1,88 @@
+use std::io;
+use std::path::PathBuf;
+
+use actix_web::{rt, web};
+use log::error;
+use once_cell::sync::OnceCell;
+use zip::write::FileOptions;
+
+use crate::DBConPool;
+use crate::models::user_data_export::{UserDataArchive, UserDataExport};
+
+const ARCHIVE_DATA_FILE_NAME: &str = "data.json";
+
+static EXPORT_DIR: OnceCell<PathBuf> = OnceCell::new();
+
+pub fn init_export_dir() {
+    let export_dir = std::env::var("EXPORT_DIR").ok().filter(|d| !d.is_empty()).expect("invalid EXPORT_DIR");
+    let export_dir = PathBuf::from(export_dir);
+    std::fs::create_dir_all(&export_dir).expect("Failed to create EXPORT_DIR");
+    
+    EXPORT_DIR.set(export_dir).expect("EXPORT_DIR is already initialized");
+}
+
+pub fn spawn(export: UserDataExport, db: DBConPool) {
+    rt::spawn(async move {
+        let (job_export, job_db) = (export.clone(), db.clone());
+        
+        let result = web::block(move || {
+            match write_archive(&job_export, &job_db) {
+                Ok(path) => job_export.complete(&path, &job_db).map(|_| ()),
+                Err(e) => {
+                    error!("failed to write data export ({}): {:?}", job_export.id, e);
+                    job_export.fail(&job_db).map(|_| ())
+                }
+            }
+        }).await;
+        
+        // Also reached when the job panicked; left pending, the export would block new requests for a week.
+        if let Err(e) = result {
+            error!("failed to finish data export ({}): {:?}", export.id, e);
+            
+            if let Err(e) = web::block(move || export.fail(&db)).await {
+                error!("failed to mark data export as failed: {:?}", e);
+            }
+        }
+    });
+}
+
+pub fn read_archive(export: &UserDataExport) -> io::Result<Vec<u8>> {
+    match &export.file_path {
+        Some(path) => std::fs::read(path),
+        None => Err(io::Error::new(io::ErrorKind::NotFound, "export has no archive")),
+    }
+}
+
+pub fn remove_expired_archives(db: &DBConPool) -> io::Result<usize> {
+    let expired_exports = UserDataExport::fetch_expired_list(db)
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+    
+    for export in &expired_exports {
+        if let Some(path) = &export.file_path {
+            match std::fs::remove_file(path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+        
+        export.delete(db).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+    }
+    
+    Ok(expired_exports.len())
+}
+
+fn write_archive(export: &UserDataExport, db: &DBConPool) -> io::Result<String> {
+    let archive = UserDataArchive::collect(&export.user_id, db)
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+    
+    let export_dir = EXPORT_DIR.get().expect("EXPORT_DIR is not initialized");
+    std::fs::create_dir_all(export_dir)?;
+    let path = export_dir.join(format!("{}.zip", export.id));
+    
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(&path)?);
+    zip.start_file(ARCHIVE_DATA_FILE_NAME, FileOptions::default())?;
+    serde_json::to_writer_pretty(&mut zip, &archive)?;
+    zip.finish()?;
+    
+    Ok(path.to_string_lossy().to_string())
+}
@@ -0,0 +1,115 @@
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use actix_web::{rt, web};
+use lettre::{SmtpTransport, Transport};
+use lettre::message::{Mailbox, Message};
+use log::{error, info};
+
+pub type SharedMailer = Arc<dyn Mailer>;
+
+pub struct Mail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+pub trait Mailer: Send + Sync {
+    fn send(&self, mail: &Mail) -> io::Result<()>;
+}
+
+/// Delivers mails through an SMTP relay given as `smtp://` or `smtps://` URL.
+pub struct SmtpMailer {
+    transport: SmtpTransport,
+    from: Mailbox,
+}
+
+/// Writes each mail as an `.eml` file, so that a local stand-in can pick them up.
+pub struct FileMailer {
+    dir: PathBuf,
+    from: Mailbox,
+}
+
+/// Only logs the mails, which is enough for development.
+pub struct LogMailer;
+
+impl SmtpMailer {
+    pub fn new(url: &str, from: Mailbox) -> io::Result<Self> {
+        let transport = SmtpTransport::from_url(url)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?
+            .build();
+        
+        Ok(Self { transport, from })
+    }
+}
+
+impl Mailer for SmtpMailer {
+    fn send(&self, mail: &Mail) -> io::Result<()> {
+        self.transport.send(&build_message(&self.from, mail)?)
+            .map(|_| ())
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+    }
+}
+
+impl FileMailer {
+    pub fn new(dir: PathBuf, from: Mailbox) -> io::Result<Self> {
+        std::fs::create_dir_all(&dir)?;
+        
+        Ok(Self { dir, from })
+    }
+}
+
+impl Mailer for FileMailer {
+    fn send(&self, mail: &Mail) -> io::Result<()> {
+        let message = build_message(&self.from, mail)?;
+        
+        std::fs::write(self.dir.join(format!("{}.eml", uuid::Uuid::new_v4())), message.formatted())
+    }
+}
+
+impl Mailer for LogMailer {
+    fn send(&self, mail: &Mail) -> io::Result<()> {
+        info!("mail to {}: {}\n{}", mail.to, mail.subject, mail.body);
+        Ok(())
+    }
+}
+
+pub fn from_env() -> SharedMailer {
+    let from = || std::env::var("MAIL_FROM").expect("invalid MAIL_FROM")
+        .parse::<Mailbox>()
+        .expect("invalid MAIL_FROM");
+    
+    match std::env::var("MAILER").as_deref() {
+        Ok("smtp") => Arc::new(
+            SmtpMailer::new(&std::env::var("SMTP_URL").expect("invalid SMTP_URL"), from()).expect("invalid SMTP_URL")
+        ),
+        Ok("file") => Arc::new(
+            FileMailer::new(PathBuf::from(std::env::var("MAIL_DIR").expect("invalid MAIL_DIR")), from()).expect("Failed to create MAIL_DIR")
+        ),
+        _ => Arc::new(LogMailer),
+    }
+}
+
+/// Sends the mail off the request path; a failed delivery is only logged.
+pub fn send_in_background(mailer: SharedMailer, mail: Mail) {
+    rt::spawn(async move {
+        let to = mail.to.clone();
+        
+        if let Err(e) = web::block(move || mailer.send(&mail)).await {
+            error!("failed to send mail to {}: {:?}", to, e);
+        }
+    });
+}
+
+fn build_message(from: &Mailbox, mail: &Mail) -> io::Result<Message> {
+    let to = mail.to.parse::<Mailbox>()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    
+    Message::builder()
+        .from(from.clone())
+        .to(to)
+        .subject(mail.subject.as_str())
+        .body(mail.body.clone())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+}
\ No newline at end of file
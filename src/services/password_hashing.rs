@@ -0,0 +1,37 @@
+use actix_web::error::BlockingError;
+use actix_web::web;
+use tokio::sync::Semaphore;
+
+const DEFAULT_MAX_CONCURRENT_HASHES: usize = 4;
+
+/// Runs work that hashes or verifies passwords off the async workers.
+/// Each Argon2 run holds its configured memory, so the number of runs at once is capped and the rest wait their turn.
+pub struct PasswordHashingPool {
+    permits: Semaphore,
+}
+
+impl PasswordHashingPool {
+    pub fn from_env() -> Self {
+        let max_concurrent_hashes = match std::env::var("ARGON2_MAX_CONCURRENCY") {
+            Ok(v) => v.parse::<usize>().expect("invalid ARGON2_MAX_CONCURRENCY"),
+            Err(_) => DEFAULT_MAX_CONCURRENT_HASHES,
+        };
+        
+        // Without a permit no password could ever be checked, and every login would wait forever.
+        if max_concurrent_hashes < 1 {
+            panic!("ARGON2_MAX_CONCURRENCY must be at least 1");
+        }
+        
+        Self { permits: Semaphore::new(max_concurrent_hashes) }
+    }
+    
+    pub async fn run<F, T>(&self, f: F) -> Result<T, BlockingError<()>>
+        where
+            F: FnOnce() -> T + Send + 'static,
+            T: Send + 'static,
+    {
+        let _permit = self.permits.acquire().await.expect("password hashing pool is closed");
+        
+        web::block(move || Ok::<T, ()>(f())).await
+    }
+}
\ No newline at end of file
@@ -0,0 +1,153 @@
+use std::io::{Cursor, Read};
+
+use actix_web::{rt, web};
+use diesel::QueryResult;
+use log::error;
+use sha2::{Digest, Sha256};
+
+use crate::DBConPool;
+use crate::models::post::{ImportedPost, Post};
+use crate::models::post_import::*;
+use crate::models::user::User;
+
+const ARCHIVE_DATA_FILE_NAME: &str = "data.json";
+const PROGRESS_UPDATE_INTERVAL: usize = 50;
+// Compressed data can expand far beyond the upload limit, so the extracted file is capped separately.
+const MAX_ARCHIVE_DATA_BYTES: u64 = 100 * 1024 * 1024;
+
+#[derive(Deserialize)]
+struct ImportRow {
+    id: Option<String>,
+    content: String,
+    published_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl ImportRow {
+    fn into_imported_post(self) -> ImportedPost {
+        // Rows without an id are identified by their content so that re-runs still skip them.
+        let source_id = self.id.unwrap_or_else(|| {
+            let mut hasher = Sha256::new();
+            hasher.update(self.published_at.map(|d| d.to_rfc3339()).unwrap_or_default());
+            hasher.update(&self.content);
+            format!("{:x}", hasher.finalize())
+        });
+        
+        ImportedPost {
+            source_id,
+            content: self.content,
+            published_at: self.published_at.map(|d| d.naive_utc()),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonImport {
+    Rows(Vec<serde_json::Value>),
+    Archive { posts: Vec<serde_json::Value> },
+}
+
+pub fn spawn(import: PostImport, author: User, payload: web::Bytes, db: DBConPool) {
+    rt::spawn(async move {
+        let (job_import, job_db) = (import.clone(), db.clone());
+        
+        let result = web::block(move || run(&job_import, &author, &payload, &job_db)).await;
+        
+        // Also reached when the job panicked, which would otherwise leave the import pending forever.
+        if let Err(e) = result {
+            error!("failed to import posts ({}): {:?}", import.id, e);
+            
+            if let Err(e) = web::block(move || import.fail(&db)).await {
+                error!("failed to mark post import as failed: {:?}", e);
+            }
+        }
+    });
+}
+
+fn run(import: &PostImport, author: &User, payload: &[u8], db: &DBConPool) -> QueryResult<()> {
+    let mut progress = PostImportProgress::default();
+    
+    let rows = match parse_rows(&import.format, payload) {
+        Ok(rows) => rows,
+        Err(message) => {
+            import.record_error(0, &message, db)?;
+            return import.update_progress(&progress, STATUS_FAILED, db).map(|_| ());
+        }
+    };
+    
+    progress.total_count = rows.len() as i32;
+    import.update_progress(&progress, STATUS_PROCESSING, db)?;
+    
+    for (i, row) in rows.into_iter().enumerate() {
+        let row_number = i as i32 + 1;
+        
+        let result = row.and_then(|r| {
+            Post::insert_imported(r.into_imported_post(), author, db)
+                .map_err(|e| format!("Invalid post: {}", e))
+        });
+        
+        match result {
+            Ok(Some(true)) => progress.imported_count += 1,
+            Ok(Some(false)) => progress.skipped_count += 1,
+            Ok(None) => {
+                progress.failed_count += 1;
+                import.record_error(row_number, "Failed to save post.", db)?;
+            }
+            Err(message) => {
+                progress.failed_count += 1;
+                import.record_error(row_number, &message, db)?;
+            }
+        }
+        
+        if (i + 1) % PROGRESS_UPDATE_INTERVAL == 0 {
+            import.update_progress(&progress, STATUS_PROCESSING, db)?;
+        }
+    }
+    
+    import.update_progress(&progress, STATUS_COMPLETED, db).map(|_| ())
+}
+
+fn parse_rows(format: &str, payload: &[u8]) -> Result<Vec<Result<ImportRow, String>>, String> {
+    match format {
+        FORMAT_ARCHIVE => parse_json_rows(&read_archive_data(payload)?),
+        FORMAT_JSON => parse_json_rows(payload),
+        FORMAT_CSV => Ok(parse_csv_rows(payload)),
+        _ => Err(format!("Unsupported format: {}", format)),
+    }
+}
+
+fn read_archive_data(payload: &[u8]) -> Result<Vec<u8>, String> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(payload))
+        .map_err(|e| format!("Invalid archive: {}", e))?;
+    let mut data_file = archive.by_name(ARCHIVE_DATA_FILE_NAME)
+        .map_err(|_| format!("Archive does not contain {}.", ARCHIVE_DATA_FILE_NAME))?;
+    
+    let mut data = Vec::new();
+    data_file.by_ref().take(MAX_ARCHIVE_DATA_BYTES + 1).read_to_end(&mut data).map_err(|e| format!("Invalid archive: {}", e))?;
+    
+    if data.len() as u64 > MAX_ARCHIVE_DATA_BYTES {
+        return Err(format!("{} exceeds {} MB when extracted.", ARCHIVE_DATA_FILE_NAME, MAX_ARCHIVE_DATA_BYTES / 1024 / 1024));
+    }
+    
+    Ok(data)
+}
+
+fn parse_json_rows(payload: &[u8]) -> Result<Vec<Result<ImportRow, String>>, String> {
+    let rows = match serde_json::from_slice::<JsonImport>(payload).map_err(|e| format!("Invalid JSON: {}", e))? {
+        JsonImport::Rows(rows) => rows,
+        JsonImport::Archive { posts } => posts,
+    };
+    
+    Ok(
+        rows.into_iter()
+            .map(|r| serde_json::from_value::<ImportRow>(r).map_err(|e| format!("Invalid row: {}", e)))
+            .collect()
+    )
+}
+
+fn parse_csv_rows(payload: &[u8]) -> Vec<Result<ImportRow, String>> {
+    csv::Reader::from_reader(payload)
+        .deserialize::<ImportRow>()
+        .map(|r| r.map_err(|e| format!("Invalid row: {}", e)))
+        .collect()
+}
@@ -0,0 +1,89 @@
+use actix_web::{HttpMessage, HttpRequest, HttpResponse};
+use actix_web::cookie::{Cookie, SameSite};
+use actix_web::dev::HttpResponseBuilder;
+use actix_web::http::Method;
+use chrono::TimeZone;
+
+use crate::models::error::{ApiError, ApiErrorCode};
+use crate::models::user_refresh_token::REFRESH_TOKEN_VALIDITY_DAYS;
+use crate::models::user_token::{ACCESS_TOKEN_VALIDITY_MINUTES, generate_token, hash_token, IssuedUserToken};
+
+pub const SESSION_COOKIE_NAME: &str = "rsns_session";
+pub const REFRESH_COOKIE_NAME: &str = "rsns_refresh";
+pub const CSRF_COOKIE_NAME: &str = "rsns_csrf";
+pub const CSRF_HEADER_NAME: &str = "X-CSRF-Token";
+pub const SESSION_MODE_HEADER_NAME: &str = "X-Session-Mode";
+
+const CSRF_TOKEN_PREFIX: &str = "rsns_csrf_";
+const SESSION_COOKIE_PATH: &str = "/api";
+const REFRESH_COOKIE_PATH: &str = "/api/auth/refresh";
+
+#[derive(Serialize)]
+struct CookieSession {
+    csrf_token: String,
+    expired_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Browsers opt in at login, so that API clients keep receiving the tokens in the body.
+pub fn is_requested(req: &HttpRequest) -> bool {
+    req.headers().get(SESSION_MODE_HEADER_NAME)
+        .and_then(|h| h.to_str().ok())
+        .map_or(false, |h| h.eq_ignore_ascii_case("cookie"))
+}
+
+/// Keeps both tokens out of the body, where script could read them. Only the CSRF token is handed out,
+/// since a frontend on another subdomain cannot read the cookie that carries it.
+pub fn token_response(issued_token: &IssuedUserToken) -> HttpResponse {
+    let csrf_token = generate_token(CSRF_TOKEN_PREFIX);
+    let session_max_age = time::Duration::minutes(ACCESS_TOKEN_VALIDITY_MINUTES);
+    let refresh_max_age = time::Duration::days(REFRESH_TOKEN_VALIDITY_DAYS);
+    
+    HttpResponse::Ok()
+        .cookie(build_cookie(SESSION_COOKIE_NAME, issued_token.token.clone(), SESSION_COOKIE_PATH, true, session_max_age))
+        .cookie(build_cookie(REFRESH_COOKIE_NAME, issued_token.refresh_token.clone(), REFRESH_COOKIE_PATH, true, refresh_max_age))
+        .cookie(build_cookie(CSRF_COOKIE_NAME, csrf_token.clone(), "/", false, refresh_max_age))
+        .json(CookieSession { csrf_token, expired_at: chrono::Utc.from_utc_datetime(&issued_token.expired_at) })
+}
+
+pub fn clear_cookies(response: &mut HttpResponseBuilder) -> &mut HttpResponseBuilder {
+    let expired = time::Duration::zero();
+    
+    response
+        .cookie(build_cookie(SESSION_COOKIE_NAME, String::new(), SESSION_COOKIE_PATH, true, expired))
+        .cookie(build_cookie(REFRESH_COOKIE_NAME, String::new(), REFRESH_COOKIE_PATH, true, expired))
+        .cookie(build_cookie(CSRF_COOKIE_NAME, String::new(), "/", false, expired))
+}
+
+pub fn session_token<T: HttpMessage>(req: &T) -> Option<String> {
+    req.cookie(SESSION_COOKIE_NAME).map(|c| c.value().to_string()).filter(|v| !v.is_empty())
+}
+
+pub fn refresh_token<T: HttpMessage>(req: &T) -> Option<String> {
+    req.cookie(REFRESH_COOKIE_NAME).map(|c| c.value().to_string()).filter(|v| !v.is_empty())
+}
+
+/// Double-submit check: another site can make the browser send the cookie, but cannot read it to copy into the header.
+pub fn verify_csrf<T: HttpMessage>(method: &Method, req: &T) -> Result<(), ApiError> {
+    if matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS) {
+        return Ok(());
+    }
+    
+    let cookie_token = req.cookie(CSRF_COOKIE_NAME).map(|c| c.value().to_string());
+    let header_token = req.headers().get(CSRF_HEADER_NAME).and_then(|h| h.to_str().ok()).map(|h| h.to_string());
+    
+    // Both sides are hashed first, so that the comparison takes the same time wherever they differ.
+    match (cookie_token, header_token) {
+        (Some(c), Some(h)) if !c.is_empty() && hash_token(&c) == hash_token(&h) => Ok(()),
+        _ => Err(ApiError::new(ApiErrorCode::InvalidCsrfToken, "CSRF token is missing or does not match.")),
+    }
+}
+
+fn build_cookie(name: &'static str, value: String, path: &'static str, http_only: bool, max_age: time::Duration) -> Cookie<'static> {
+    Cookie::build(name, value)
+        .path(path)
+        .http_only(http_only)
+        .secure(true)
+        .same_site(SameSite::Strict)
+        .max_age(max_age)
+        .finish()
+}
\ No newline at end of file
@@ -5,28 +5,41 @@ use std::{
 };
 
 use actix_web::{Error, HttpMessage, web};
+use actix_web::http::Method;
 use actix_web::dev::{Service, ServiceRequest, ServiceResponse, Transform};
 use actix_web::http::header::Header;
 use actix_web::web::Data;
 use actix_web_httpauth::headers::authorization::{Authorization, Bearer};
+use log::error;
 
 use crate::DBConPool;
 use crate::models::error::{ApiError, ApiErrorCode};
+use crate::models::personal_access_token::{PERSONAL_ACCESS_TOKEN_PREFIX, PersonalAccessToken};
+use crate::models::token_scope::TokenScope;
 use crate::models::user::User;
 use crate::models::user_credential::UserCredential;
+use crate::models::user_session::UserSession;
 use crate::models::user_token::UserToken;
+use crate::services::session_cookie;
 
 pub struct TokenAuthentication {
     auth_required: bool,
+    method_scopes: Vec<(Method, TokenScope)>,
 }
 
 impl TokenAuthentication {
     pub fn required() -> Self {
-        Self { auth_required: true }
+        Self { auth_required: true, method_scopes: vec![] }
     }
     
     pub fn unnecessary() -> Self {
-        Self { auth_required: false }
+        Self { auth_required: false, method_scopes: vec![] }
+    }
+    
+    // Methods without an explicit scope require `TokenScope::Account`, which only login sessions hold.
+    pub fn require_scope(mut self, method: Method, scope: TokenScope) -> Self {
+        self.method_scopes.push((method, scope));
+        self
     }
 }
 
@@ -47,6 +60,7 @@ impl<S, B> Transform<S> for TokenAuthentication
         ready(Ok(TokenAuthenticationMiddleware {
             service,
             auth_required: self.auth_required,
+            method_scopes: self.method_scopes.clone(),
         }))
     }
 }
@@ -54,6 +68,7 @@ impl<S, B> Transform<S> for TokenAuthentication
 pub struct TokenAuthenticationMiddleware<S> {
     service: S,
     auth_required: bool,
+    method_scopes: Vec<(Method, TokenScope)>,
 }
 
 impl<S, B> Service for TokenAuthenticationMiddleware<S>
@@ -74,14 +89,23 @@ impl<S, B> Service for TokenAuthenticationMiddleware<S>
     fn call(&mut self, request: ServiceRequest) -> Self::Future {
         let database = request.app_data::<web::Data<DBConPool>>().expect("DBConPool was not wrapped in App");
         
-        let result = match Authorization::<Bearer>::parse(&request) {
-            Ok(t) => {
-                validate_token(&request, &database, t.into_scheme().token().to_string())
+        let required_scope = self.method_scopes.iter()
+            .find(|(m, _)| m == request.method())
+            .map_or(TokenScope::Account, |(_, s)| *s);
+        
+        // Another site cannot attach a bearer header, so only a cookie-borne token needs the CSRF check.
+        let result = match (Authorization::<Bearer>::parse(&request), session_cookie::session_token(&request)) {
+            (Ok(t), _) => {
+                validate_token(&request, &database, t.into_scheme().token().to_string(), required_scope)
+            }
+            (Err(_), Some(t)) => {
+                session_cookie::verify_csrf(request.method(), &request)
+                    .and_then(|_| validate_token(&request, &database, t, required_scope))
             }
-            Err(_) if !self.auth_required => {
+            (Err(_), None) if !self.auth_required => {
                 Ok(())
             }
-            Err(_) => {
+            (Err(_), None) => {
                 Err(ApiError::new(ApiErrorCode::AuthFailed, "Authorization required."))
             }
         };
@@ -96,19 +120,42 @@ impl<S, B> Service for TokenAuthenticationMiddleware<S>
 #[derive(Clone)]
 pub struct AuthorizedUser {
     pub token: String,
+    pub session_id: Option<String>,
     pub credential: UserCredential,
     pub user: Option<User>,
 }
 
-fn validate_token(request: &ServiceRequest, database: &Data<DBConPool>, t: String) -> Result<(), ApiError> {
-    UserToken::verify_token(&t, &database)
-        .map(|t| {
-            request.extensions_mut().insert(
-                AuthorizedUser {
-                    token: t.token,
-                    credential: UserCredential::fetch_by_id(&t.user_id, &database).expect("Token was authenticated but failed to fetch UserCredential"),
-                    user: User::fetch_by_id(&t.user_id, &database).ok(),
-                }
-            );
-        })
+fn validate_token(request: &ServiceRequest, database: &Data<DBConPool>, t: String, required_scope: TokenScope) -> Result<(), ApiError> {
+    let (user_id, session_id, scopes) = if t.starts_with(PERSONAL_ACCESS_TOKEN_PREFIX) {
+        let personal_access_token = PersonalAccessToken::verify_token(&t, &database)?;
+        let scopes = personal_access_token.scope_list();
+        (personal_access_token.user_id, None, scopes)
+    } else {
+        let (user_token, scopes) = UserToken::verify_token(&t, &database)?;
+        (user_token.user_id, Some(user_token.family_id), scopes)
+    };
+    
+    if !scopes.contains(&required_scope) {
+        return Err(ApiError::new(ApiErrorCode::InsufficientScope, "Token does not have the required scope."));
+    }
+    
+    if let Some(session_id) = &session_id {
+        if let Err(e) = UserSession::touch(session_id, &database) {
+            error!("failed to update session ({}): {:?}", session_id, e);
+        }
+    }
+    
+    let credential = UserCredential::fetch_by_id(&user_id, &database)
+        .map_err(|_| ApiError::new(ApiErrorCode::InvalidToken, "Invalid token."))?;
+    
+    request.extensions_mut().insert(
+        AuthorizedUser {
+            token: t,
+            session_id,
+            credential,
+            user: User::fetch_by_id(&user_id, &database).ok(),
+        }
+    );
+    
+    Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,19 @@
+use log::{error, info};
+
+use crate::DBConPool;
+use crate::models::user_refresh_token::UserRefreshToken;
+use crate::models::user_token::UserToken;
+
+pub fn hash_legacy_tokens(db: &DBConPool) {
+    match UserToken::hash_legacy_tokens(db) {
+        Ok(count) if count > 0 => info!("hashed {} legacy access tokens", count),
+        Ok(_) => {}
+        Err(e) => error!("failed to hash legacy access tokens: {:?}", e),
+    }
+    
+    match UserRefreshToken::hash_legacy_tokens(db) {
+        Ok(count) if count > 0 => info!("hashed {} legacy refresh tokens", count),
+        Ok(_) => {}
+        Err(e) => error!("failed to hash legacy refresh tokens: {:?}", e),
+    }
+}
\ No newline at end of file
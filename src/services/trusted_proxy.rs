@@ -0,0 +1,96 @@
+use std::net::IpAddr;
+
+use actix_web::HttpRequest;
+use once_cell::sync::OnceCell;
+
+const FORWARDED_FOR_HEADER_NAME: &str = "X-Forwarded-For";
+
+static TRUSTED_PROXIES: OnceCell<Vec<IpAddr>> = OnceCell::new();
+
+/// Reads the comma-separated addresses of the reverse proxies in front of the server from `TRUSTED_PROXIES`.
+pub fn init_from_env() {
+    let trusted_proxies = std::env::var("TRUSTED_PROXIES")
+        .unwrap_or_default()
+        .split(',')
+        .map(|a| a.trim())
+        .filter(|a| !a.is_empty())
+        .map(|a| a.parse::<IpAddr>().expect("invalid TRUSTED_PROXIES"))
+        .collect::<Vec<IpAddr>>();
+    
+    TRUSTED_PROXIES.set(trusted_proxies).expect("TRUSTED_PROXIES is already initialized");
+}
+
+/// The address of the client, without the port, which changes with every connection.
+/// `X-Forwarded-For` is only read when the connection comes from a trusted proxy, since anyone else can send it.
+pub fn client_ip(request: &HttpRequest) -> Option<IpAddr> {
+    let peer_ip = request.peer_addr()?.ip();
+    let trusted_proxies = TRUSTED_PROXIES.get().expect("TRUSTED_PROXIES is not initialized");
+    let forwarded_for = request.headers().get_all(FORWARDED_FOR_HEADER_NAME)
+        .filter_map(|h| h.to_str().ok())
+        .collect::<Vec<&str>>();
+    
+    Some(resolve_client_ip(peer_ip, trusted_proxies, &forwarded_for))
+}
+
+fn resolve_client_ip(peer_ip: IpAddr, trusted_proxies: &[IpAddr], forwarded_for: &[&str]) -> IpAddr {
+    if !trusted_proxies.contains(&peer_ip) {
+        return peer_ip;
+    }
+    
+    // Each proxy appends the address it received the request from, so the entries left of our own proxies
+    // were written by the client and are skipped by reading from the right.
+    let forwarded_ips = forwarded_for.iter()
+        .flat_map(|h| h.split(','))
+        .map(|a| a.trim().parse::<IpAddr>().ok())
+        .collect::<Vec<Option<IpAddr>>>();
+    
+    for forwarded_ip in forwarded_ips.into_iter().rev() {
+        match forwarded_ip {
+            Some(ip) if trusted_proxies.contains(&ip) => continue,
+            Some(ip) => return ip,
+            None => break,
+        }
+    }
+    
+    peer_ip
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn ip(address: &str) -> IpAddr {
+        address.parse().expect("test address should parse")
+    }
+    
+    fn trusted_proxies() -> Vec<IpAddr> {
+        vec![ip("10.0.0.1"), ip("10.0.0.2")]
+    }
+    
+    #[test]
+    fn resolve_client_ip_ignores_header_from_untrusted_peer() {
+        let resolved_ip = resolve_client_ip(ip("203.0.113.7"), &trusted_proxies(), &["198.51.100.1"]);
+        
+        assert_eq!(resolved_ip, ip("203.0.113.7"));
+    }
+    
+    #[test]
+    fn resolve_client_ip_skips_chain_of_trusted_proxies() {
+        let resolved_ip = resolve_client_ip(ip("10.0.0.1"), &trusted_proxies(), &["198.51.100.1, 203.0.113.7", "10.0.0.2"]);
+        
+        assert_eq!(resolved_ip, ip("203.0.113.7"));
+    }
+    
+    #[test]
+    fn resolve_client_ip_stops_at_unparsable_entry() {
+        let resolved_ip = resolve_client_ip(ip("10.0.0.1"), &trusted_proxies(), &["203.0.113.7, unknown, 10.0.0.2"]);
+        
+        assert_eq!(resolved_ip, ip("10.0.0.1"));
+    }
+    
+    #[test]
+    fn resolve_client_ip_falls_back_to_peer_without_header() {
+        assert_eq!(resolve_client_ip(ip("10.0.0.1"), &trusted_proxies(), &[]), ip("10.0.0.1"));
+        assert_eq!(resolve_client_ip(ip("10.0.0.1"), &trusted_proxies(), &[""]), ip("10.0.0.1"));
+    }
+}
\ No newline at end of file